@@ -0,0 +1,13 @@
+create table price_history (
+    market_hash_name varchar(128) not null,
+    recorded_at timestamptz not null,
+    steam_last_24h real,
+    steam_last_7d real,
+    steam_last_30d real,
+    steam_last_90d real,
+    skinport_suggested real,
+    skinport_starting_at real,
+    buff163_starting_at real,
+    buff163_highest_order real,
+    primary key (market_hash_name, recorded_at)
+);
@@ -0,0 +1,25 @@
+create type transaction_kind as enum ('buy', 'sell');
+
+create table transactions (
+    tx_id int generated always as identity primary key,
+    inv_id int not null,
+    steam_id varchar(18) not null,
+    kind transaction_kind not null,
+    amount int not null check (amount > 0),
+    price numeric(10, 2) not null,
+    currency currencies not null default 'USD',
+    created_at timestamptz not null default now(),
+    constraint fk_owner_tx
+        foreign key (steam_id)
+        references users (steam_id),
+    constraint fk_investment_tx
+        foreign key (inv_id)
+        references investments (inv_id)
+        on delete cascade
+);
+
+insert into transactions
+(inv_id, steam_id, kind, amount, price, currency)
+select inv_id, steam_id, 'buy', amount, cost, coalesce(currency, 'USD')
+from investments
+where amount > 0;
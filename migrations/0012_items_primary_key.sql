@@ -0,0 +1,6 @@
+delete from items a
+using items b
+where a.ctid < b.ctid and a.market_hash_name = b.market_hash_name;
+
+alter table items
+add primary key (market_hash_name);
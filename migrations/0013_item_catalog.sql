@@ -0,0 +1,19 @@
+create table item_catalog (
+    market_hash_name varchar(128) primary key,
+    item_type varchar(32) not null,
+    name varchar(128) not null,
+    weapon varchar(64),
+    skin varchar(64),
+    exterior varchar(32),
+    rarity varchar(64),
+    collection varchar(128),
+    min_float real,
+    max_float real,
+    stattrak boolean not null default false,
+    souvenir boolean not null default false,
+    image varchar(512)
+);
+
+create index item_catalog_item_type_idx on item_catalog (item_type);
+
+create index item_catalog_weapon_skin_idx on item_catalog (weapon, skin);
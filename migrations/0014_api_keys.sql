@@ -0,0 +1,19 @@
+create type api_key_scope as enum ('read', 'read_write');
+
+create table api_keys (
+    key_id int generated always as identity primary key,
+    steam_id varchar(18) not null,
+    user_id int not null,
+    name varchar(64) not null,
+    prefix varchar(16) not null,
+    key_hash varchar(64) not null unique,
+    scope api_key_scope not null,
+    created_at timestamptz not null default now(),
+    last_used_at timestamptz,
+    revoked_at timestamptz,
+    constraint fk_owner_key
+        foreign key (steam_id)
+        references users (steam_id)
+);
+
+create index api_keys_steam_id_idx on api_keys (steam_id);
@@ -0,0 +1 @@
+create index items_market_hash_name_trgm_idx on items using gin(market_hash_name gin_trgm_ops);
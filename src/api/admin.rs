@@ -0,0 +1,47 @@
+use std::env;
+
+use axum::{extract::State, routing::get, Extension, Json, Router};
+use serde::Serialize;
+
+use crate::{
+    error::{Error, Result},
+    jobs::JobStatus,
+    jwt::User,
+    state::AppState,
+};
+
+pub fn routes() -> Router<AppState> {
+    Router::new().route("/jobs", get(job_statuses))
+}
+
+/// Admins are listed by steam id in the comma separated `ADMIN_STEAM_IDS`.
+fn require_admin(user: User) -> Result<()> {
+    let steam_id = user.steam_id()?;
+
+    let is_admin = env::var("ADMIN_STEAM_IDS")
+        .unwrap_or_default()
+        .split(',')
+        .any(|id| id.trim() == steam_id);
+
+    if !is_admin {
+        return Err(Error::AuthNotAdmin);
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct Jobs {
+    jobs: Vec<JobStatus>,
+}
+
+async fn job_statuses(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+) -> Result<Json<Jobs>> {
+    require_admin(user)?;
+
+    Ok(Json(Jobs {
+        jobs: state.jobs.statuses(),
+    }))
+}
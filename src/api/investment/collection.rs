@@ -6,10 +6,18 @@ use axum::{
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    db::collection::{
-        create_collection, drop_collection, get_collections, update_collection, Collection,
+    api::{fetch_inventory, PriceSource},
+    db::{
+        collection::{
+            collection_exists, create_collection, drop_collection, get_collections,
+            update_collection, Collection,
+        },
+        investment::{
+            create_investments, get_tracked_items, Currencies, CustomInvestment, NewInvestment,
+        },
+        item::get_items_prices,
     },
-    error::Result,
+    error::{Error, Result},
     jwt::User,
     state::AppState,
 };
@@ -20,6 +28,7 @@ pub fn routes() -> Router<AppState> {
         .route("/all", get(all_collections))
         .route("/:coll_id", delete(delete_collection))
         .route("/:coll_id", post(rename_collection))
+        .route("/:coll_id/import-inventory", post(import_inventory))
 }
 
 #[derive(Deserialize)]
@@ -71,3 +80,65 @@ async fn rename_collection(
         update_collection(&state.pg, user.steam_id()?, col_id, body.name).await?,
     ))
 }
+
+#[derive(Serialize)]
+struct ImportedInventory {
+    imported: Vec<CustomInvestment>,
+    skipped: Vec<String>,
+}
+
+/// Creates investments for inventory items not yet tracked in the collection,
+/// using the current steam price as their cost.
+///
+/// Items without a steam price are skipped. Either every investment is created or none.
+async fn import_inventory(
+    Path(col_id): Path<i32>,
+    State(mut state): State<AppState>,
+    Extension(user): Extension<User>,
+) -> Result<Json<ImportedInventory>> {
+    let steam_id = user.steam_id()?;
+
+    if !collection_exists(&state.pg, &steam_id, col_id).await? {
+        return Err(Error::PgRowNotFound);
+    }
+
+    let inventory = fetch_inventory(&mut state.redis, &steam_id).await?;
+    let tracked = get_tracked_items(&state.pg, &steam_id, col_id).await?;
+
+    let items: Vec<_> = inventory
+        .items
+        .into_iter()
+        .filter(|item| item.marketable && !tracked.contains(&item.market_hash_name))
+        .collect();
+
+    let names: Vec<String> = items
+        .iter()
+        .map(|item| item.market_hash_name.clone())
+        .collect();
+
+    let prices = get_items_prices(&mut state, &names).await?;
+
+    let mut investments = vec![];
+    let mut skipped = vec![];
+
+    for item in items.into_iter() {
+        let cost = prices
+            .get(&item.market_hash_name)
+            .and_then(|lookup| lookup.as_ref().ok())
+            .and_then(|prices| prices.price(PriceSource::Steam));
+
+        match cost {
+            Some(cost) => investments.push(NewInvestment {
+                item: item.market_hash_name,
+                cost,
+                amount: item.count as i32,
+            }),
+            None => skipped.push(item.market_hash_name),
+        }
+    }
+
+    let imported =
+        create_investments(&state.pg, &steam_id, col_id, investments, Currencies::USD).await?;
+
+    Ok(Json(ImportedInventory { imported, skipped }))
+}
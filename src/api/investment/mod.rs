@@ -4,7 +4,6 @@ use axum::{
     routing::{delete, get, post},
     Extension, Json, Router,
 };
-use http::HeaderMap;
 use serde::{Deserialize, Serialize};
 
 use crate::{
@@ -13,24 +12,28 @@ use crate::{
             create_investment, drop_investment, get_investments, get_investments_by_coll,
             update_investment, Currencies, CustomInvestment,
         },
-        item::{get_item_prices, item_exists, suggest_items, Item},
+        item::{get_items_prices, item_exists, suggest_items, Item},
     },
     error::{Error, Result},
     jwt::User,
     state::AppState,
+    steam::market_hash_name::Exterior,
 };
 
-use super::Prices;
+use super::{target_rates, Conversion, Prices, UnpricedReason};
 
 pub mod collection;
+pub mod summary;
+pub mod transaction;
 
 pub fn routes() -> Router<AppState> {
     Router::new()
         .route("/create", post(new_investment))
         .route("/all", get(all_investments))
-        .route("/suggestion", get(get_suggestion))
+        .route("/summary", get(summary::investment_summary))
         .route("/:inv_id", delete(delete_investment))
         .route("/:inv_id", post(edit_investment))
+        .nest("/:inv_id/transactions", transaction::routes())
         .nest("/collection", collection::routes())
 }
 
@@ -48,9 +51,11 @@ async fn new_investment(
     Extension(user): Extension<User>,
     Json(body): Json<InvestmentReq>,
 ) -> Result<Json<CustomInvestment>> {
-    match item_exists(&mut state, &body.market_hash_name).await? {
-        None => return Err(Error::InvalidHashName),
-        _ => (),
+    if item_exists(&mut state, &body.market_hash_name)
+        .await?
+        .is_none()
+    {
+        return Err(Error::InvalidHashName(body.market_hash_name));
     }
 
     Ok(Json(
@@ -70,6 +75,9 @@ async fn new_investment(
 #[derive(Serialize)]
 struct Investments {
     investments: Vec<PricedInvestment>,
+    unpriced: Vec<UnpricedInvestment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    conversion: Option<Conversion>,
 }
 
 #[derive(Serialize)]
@@ -78,9 +86,16 @@ struct PricedInvestment {
     prices: Prices,
 }
 
+#[derive(Serialize)]
+struct UnpricedInvestment {
+    investment: CustomInvestment,
+    reason: UnpricedReason,
+}
+
 #[derive(Deserialize)]
 struct InvestmentQuery {
     col_id: Option<i32>,
+    currency: Option<Currencies>,
 }
 
 async fn all_investments(
@@ -93,21 +108,41 @@ async fn all_investments(
         _ => get_investments(&state.pg, user.steam_id()?).await?,
     };
 
-    let mut priced_investments = vec![];
+    let rates = target_rates(&mut state, &query.currency).await?;
+    let target = rates.as_ref().zip(query.currency.as_ref());
 
-    for investment in investments.into_iter() {
-        priced_investments.push(PricedInvestment {
-            prices: get_item_prices(&mut state, &investment.item).await?,
-            investment,
-        });
-    }
-
-    let mut headers = HeaderMap::new();
+    let names: Vec<String> = investments.iter().map(|i| i.item.clone()).collect();
+    let items_prices = get_items_prices(&mut state, &names).await?;
 
-    // headers.insert("Cache-Control", "public, max-age=");
+    let mut priced_investments = vec![];
+    let mut unpriced = vec![];
+
+    for mut investment in investments.into_iter() {
+        let lookup = items_prices
+            .get(&investment.item)
+            .cloned()
+            .unwrap_or(Err(UnpricedReason::UnknownItem));
+
+        if let Some((rates, currency)) = target {
+            investment.convert(rates, currency);
+        }
+
+        match lookup {
+            Ok(mut prices) => {
+                if let Some((rates, currency)) = target {
+                    prices = prices.scaled(rates.rate(currency));
+                }
+
+                priced_investments.push(PricedInvestment { prices, investment });
+            }
+            Err(reason) => unpriced.push(UnpricedInvestment { investment, reason }),
+        }
+    }
 
     Ok((Json(Investments {
         investments: priced_investments,
+        unpriced,
+        conversion: rates.zip(query.currency).map(|(r, c)| r.conversion(c)),
     }))
     .into_response())
 }
@@ -140,20 +175,25 @@ async fn edit_investment(
 }
 
 #[derive(Deserialize)]
-struct SuggestionReq {
-    q: String,
+pub struct SuggestionReq {
+    pub q: String,
+    pub limit: Option<i64>,
+    #[serde(rename = "type")]
+    pub item_type: Option<String>,
+    pub stattrak: Option<bool>,
+    pub exterior: Option<Exterior>,
 }
 
 #[derive(Serialize)]
-struct Suggestions {
+pub struct Suggestions {
     suggestions: Vec<Item>,
 }
 
-async fn get_suggestion(
+pub async fn get_suggestion(
     Query(query): Query<SuggestionReq>,
     State(state): State<AppState>,
 ) -> Result<Json<Suggestions>> {
     Ok(Json(Suggestions {
-        suggestions: suggest_items(&state.pg, query.q).await?,
+        suggestions: suggest_items(&state.pg, query).await?,
     }))
 }
@@ -0,0 +1,304 @@
+use axum::{
+    extract::{Query, State},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{CurrencyRates, PriceSource, UnpricedReason},
+    db::{
+        investment::{get_investments, get_investments_by_coll, Currencies, CustomInvestment},
+        item::get_items_prices,
+        rate::get_currency_rates,
+    },
+    error::Result,
+    jwt::User,
+    state::AppState,
+};
+
+#[derive(Deserialize)]
+pub struct SummaryQuery {
+    col_id: Option<i32>,
+    currency: Option<Currencies>,
+    #[serde(default)]
+    source: PriceSource,
+}
+
+#[derive(Serialize, Default, Clone, Copy)]
+pub struct Totals {
+    cost_basis: f64,
+    current_value: f64,
+    profit: f64,
+    profit_pct: Option<f64>,
+}
+
+impl Totals {
+    fn new(cost_basis: f64, current_value: f64) -> Self {
+        let profit = current_value - cost_basis;
+
+        Self {
+            cost_basis,
+            current_value,
+            profit,
+            profit_pct: (cost_basis > 0.0).then(|| profit / cost_basis * 100.0),
+        }
+    }
+
+    fn add(self, other: Totals) -> Self {
+        Self::new(
+            self.cost_basis + other.cost_basis,
+            self.current_value + other.current_value,
+        )
+    }
+}
+
+#[derive(Serialize)]
+pub struct InvestmentSummary {
+    inv_id: i32,
+    item: String,
+    col_id: i32,
+    amount: i32,
+    unit_price: f64,
+    #[serde(flatten)]
+    totals: Totals,
+}
+
+/// Investment left out of the totals because its item has no price from the source.
+#[derive(Serialize)]
+pub struct UnpricedSummary {
+    inv_id: i32,
+    item: String,
+    col_id: i32,
+    amount: i32,
+    reason: UnpricedReason,
+}
+
+#[derive(Serialize)]
+pub struct CollectionSummary {
+    col_id: i32,
+    name: String,
+    #[serde(flatten)]
+    totals: Totals,
+}
+
+#[derive(Serialize)]
+pub struct Summary {
+    currency: Currencies,
+    source: PriceSource,
+    investments: Vec<InvestmentSummary>,
+    unpriced: Vec<UnpricedSummary>,
+    collections: Vec<CollectionSummary>,
+    total: Totals,
+}
+
+pub async fn investment_summary(
+    Query(query): Query<SummaryQuery>,
+    State(mut state): State<AppState>,
+    Extension(user): Extension<User>,
+) -> Result<Json<Summary>> {
+    let investments = match query.col_id {
+        Some(col_id) => get_investments_by_coll(&state.pg, user.steam_id()?, col_id).await?,
+        _ => get_investments(&state.pg, user.steam_id()?).await?,
+    };
+
+    let currency = query.currency.unwrap_or(Currencies::USD);
+    let rates = get_currency_rates(&mut state.redis).await?;
+
+    let names: Vec<String> = investments.iter().map(|i| i.item.clone()).collect();
+    let items_prices = get_items_prices(&mut state, &names).await?;
+
+    let mut priced = vec![];
+    let mut summaries = vec![];
+    let mut unpriced = vec![];
+
+    for investment in investments.iter() {
+        let unit_price = match items_prices.get(&investment.item) {
+            Some(Ok(prices)) => prices.price(query.source).ok_or(UnpricedReason::NoPrice),
+            Some(Err(reason)) => Err(*reason),
+            _ => Err(UnpricedReason::UnknownItem),
+        };
+
+        match unit_price {
+            Ok(price) => {
+                let unit_price = rates.convert(price as f64, &Currencies::USD, &currency);
+
+                priced.push(investment);
+                summaries.push(summarize(investment, unit_price, &rates, &currency));
+            }
+            Err(reason) => unpriced.push(UnpricedSummary {
+                inv_id: investment.inv_id,
+                item: investment.item.clone(),
+                col_id: investment.collection,
+                amount: investment.amount,
+                reason,
+            }),
+        }
+    }
+
+    let (collections, total) = add_up(&priced, &summaries);
+
+    Ok(Json(Summary {
+        currency,
+        source: query.source,
+        investments: summaries,
+        unpriced,
+        collections,
+        total,
+    }))
+}
+
+/// Totals per collection, in the order they first appear, and over all of `summaries`,
+/// which belong to the `priced` investments at the same index.
+fn add_up(
+    priced: &[&CustomInvestment],
+    summaries: &[InvestmentSummary],
+) -> (Vec<CollectionSummary>, Totals) {
+    let mut collections: Vec<CollectionSummary> = vec![];
+
+    for (investment, summary) in priced.iter().zip(summaries.iter()) {
+        match collections
+            .iter_mut()
+            .find(|c| c.col_id == investment.collection)
+        {
+            Some(collection) => collection.totals = collection.totals.add(summary.totals),
+            _ => collections.push(CollectionSummary {
+                col_id: investment.collection,
+                name: investment.col_name.clone(),
+                totals: summary.totals,
+            }),
+        }
+    }
+
+    let total = summaries
+        .iter()
+        .fold(Totals::default(), |acc, s| acc.add(s.totals));
+
+    (collections, total)
+}
+
+fn summarize(
+    investment: &CustomInvestment,
+    unit_price: f64,
+    rates: &CurrencyRates,
+    currency: &Currencies,
+) -> InvestmentSummary {
+    let cost = f64::try_from(investment.cost).unwrap_or_default();
+    let cost = rates.convert(cost, &investment.currency, currency);
+    let amount = investment.amount as f64;
+
+    InvestmentSummary {
+        inv_id: investment.inv_id,
+        item: investment.item.clone(),
+        col_id: investment.collection,
+        amount: investment.amount,
+        unit_price,
+        totals: Totals::new(cost * amount, unit_price * amount),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn rates() -> CurrencyRates {
+        serde_json::from_str(
+            r#"{"EUR":0.5,"CNY":8.0,"TRY":32.0,"PLN":4.0,"GBP":0.25,"UAH":40.0,"KRW":1024.0,"BRL":5.0}"#,
+        )
+        .unwrap()
+    }
+
+    fn investment(
+        inv_id: i32,
+        col_id: i32,
+        cost: f64,
+        amount: i32,
+        currency: &str,
+    ) -> CustomInvestment {
+        serde_json::from_value(json!({
+            "inv_id": inv_id,
+            "steam_id": "76561198000000000",
+            "item": "AK-47 | Redline (Field-Tested)",
+            "collection": col_id,
+            "col_name": format!("collection {col_id}"),
+            "cost": cost.to_string(),
+            "amount": amount,
+            "currency": currency,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn totals_are_cost_and_price_times_amount() {
+        let summary = summarize(
+            &investment(1, 1, 10.0, 3, "USD"),
+            15.0,
+            &rates(),
+            &Currencies::USD,
+        );
+
+        assert_eq!(summary.totals.cost_basis, 30.0);
+        assert_eq!(summary.totals.current_value, 45.0);
+        assert_eq!(summary.totals.profit, 15.0);
+        assert_eq!(summary.totals.profit_pct, Some(50.0));
+    }
+
+    #[test]
+    fn cost_is_converted_from_investment_currency() {
+        // 5 EUR is 10 USD, which is 2.5 GBP
+        let summary = summarize(
+            &investment(1, 1, 5.0, 2, "EUR"),
+            4.0,
+            &rates(),
+            &Currencies::GBP,
+        );
+
+        assert_eq!(summary.totals.cost_basis, 5.0);
+        assert_eq!(summary.totals.current_value, 8.0);
+        assert_eq!(summary.totals.profit_pct, Some(60.0));
+    }
+
+    #[test]
+    fn zero_cost_basis_has_no_profit_pct() {
+        let summary = summarize(
+            &investment(1, 1, 0.0, 2, "USD"),
+            4.0,
+            &rates(),
+            &Currencies::USD,
+        );
+
+        assert_eq!(summary.totals.profit, 8.0);
+        assert_eq!(summary.totals.profit_pct, None);
+    }
+
+    #[test]
+    fn adds_up_collections_and_total() {
+        let investments = [
+            investment(1, 1, 10.0, 1, "USD"),
+            investment(2, 2, 20.0, 2, "USD"),
+            investment(3, 1, 30.0, 1, "USD"),
+        ];
+        let priced: Vec<&CustomInvestment> = investments.iter().collect();
+
+        let summaries: Vec<InvestmentSummary> = priced
+            .iter()
+            .map(|i| summarize(i, 20.0, &rates(), &Currencies::USD))
+            .collect();
+
+        let (collections, total) = add_up(&priced, &summaries);
+
+        assert_eq!(collections.len(), 2);
+        assert_eq!(collections[0].col_id, 1);
+        assert_eq!(collections[0].totals.cost_basis, 40.0);
+        assert_eq!(collections[0].totals.current_value, 40.0);
+        assert_eq!(collections[0].totals.profit_pct, Some(0.0));
+        assert_eq!(collections[1].col_id, 2);
+        assert_eq!(collections[1].totals.cost_basis, 40.0);
+        assert_eq!(collections[1].totals.current_value, 40.0);
+
+        assert_eq!(total.cost_basis, 80.0);
+        assert_eq!(total.current_value, 80.0);
+        assert_eq!(total.profit, 0.0);
+    }
+}
@@ -0,0 +1,286 @@
+use std::collections::VecDeque;
+
+use axum::{
+    extract::{Path, Query, State},
+    routing::{delete, get},
+    Extension, Json, Router,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{CurrencyRates, PriceSource},
+    db::{
+        investment::{get_investment, Currencies},
+        item::get_items_prices,
+        rate::get_currency_rates,
+        transaction::{
+            create_transaction, drop_transaction, get_transactions, Transaction, TransactionKind,
+        },
+    },
+    error::Result,
+    jwt::User,
+    state::AppState,
+};
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(all_transactions).post(new_transaction))
+        .route("/:tx_id", delete(delete_transaction))
+}
+
+#[derive(Deserialize)]
+struct TransactionReq {
+    kind: TransactionKind,
+    amount: i32,
+    price: f32,
+    currency: Currencies,
+}
+
+async fn new_transaction(
+    Path(inv_id): Path<i32>,
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    Json(body): Json<TransactionReq>,
+) -> Result<Json<Transaction>> {
+    Ok(Json(
+        create_transaction(
+            &state.pg,
+            user.steam_id()?,
+            inv_id,
+            body.kind,
+            body.amount,
+            body.price,
+            body.currency,
+        )
+        .await?,
+    ))
+}
+
+async fn delete_transaction(
+    Path((inv_id, tx_id)): Path<(i32, i32)>,
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+) -> Result<()> {
+    drop_transaction(&state.pg, user.steam_id()?, inv_id, tx_id).await
+}
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+enum CostMethod {
+    #[default]
+    Fifo,
+    Average,
+}
+
+#[derive(Deserialize)]
+struct GainsQuery {
+    #[serde(default)]
+    method: CostMethod,
+    currency: Option<Currencies>,
+    #[serde(default)]
+    source: PriceSource,
+}
+
+#[derive(Serialize, Default)]
+struct Gains {
+    open_amount: i32,
+    open_cost: f64,
+    realized: f64,
+    unrealized: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct Transactions {
+    currency: Currencies,
+    transactions: Vec<Transaction>,
+    gains: Gains,
+}
+
+async fn all_transactions(
+    Path(inv_id): Path<i32>,
+    Query(query): Query<GainsQuery>,
+    State(mut state): State<AppState>,
+    Extension(user): Extension<User>,
+) -> Result<Json<Transactions>> {
+    let steam_id = user.steam_id()?;
+    let investment = get_investment(&state.pg, &steam_id, inv_id).await?;
+    let transactions = get_transactions(&state.pg, steam_id, inv_id).await?;
+
+    let currency = query.currency.unwrap_or(Currencies::USD);
+    let rates = get_currency_rates(&mut state.redis).await?;
+
+    let mut gains = compute_gains(&transactions, query.method, &rates, &currency);
+
+    if !transactions.is_empty() {
+        let prices = get_items_prices(&mut state, std::slice::from_ref(&investment.item))
+            .await?
+            .remove(&investment.item)
+            .and_then(|lookup| lookup.ok());
+
+        gains.unrealized = prices.and_then(|p| p.price(query.source)).map(|price| {
+            let price = rates.convert(price as f64, &Currencies::USD, &currency);
+
+            price * gains.open_amount as f64 - gains.open_cost
+        });
+    }
+
+    Ok(Json(Transactions {
+        currency,
+        transactions,
+        gains,
+    }))
+}
+
+/// Matches sells against buy lots, either first-in-first-out or at average cost.
+fn compute_gains(
+    transactions: &[Transaction],
+    method: CostMethod,
+    rates: &CurrencyRates,
+    currency: &Currencies,
+) -> Gains {
+    let mut lots: VecDeque<(i32, f64)> = VecDeque::new();
+    let mut gains = Gains::default();
+
+    for tx in transactions.iter() {
+        let price = f64::try_from(tx.price).unwrap_or_default();
+        let price = rates.convert(price, &tx.currency, currency);
+
+        match tx.kind {
+            TransactionKind::Buy => match method {
+                CostMethod::Fifo => lots.push_back((tx.amount, price)),
+                CostMethod::Average => {
+                    let (amount, cost) = lots.pop_front().unwrap_or_default();
+                    let total = amount as f64 * cost + tx.amount as f64 * price;
+                    let amount = amount + tx.amount;
+
+                    lots.push_back((amount, total / amount as f64));
+                }
+            },
+            TransactionKind::Sell => {
+                let mut remaining = tx.amount;
+
+                while remaining > 0 {
+                    let Some(lot) = lots.front_mut() else {
+                        break;
+                    };
+
+                    let matched = remaining.min(lot.0);
+
+                    gains.realized += matched as f64 * (price - lot.1);
+                    lot.0 -= matched;
+                    remaining -= matched;
+
+                    if lot.0 == 0 {
+                        lots.pop_front();
+                    }
+                }
+            }
+        }
+    }
+
+    gains.open_amount = lots.iter().map(|(amount, _)| amount).sum();
+    gains.open_cost = lots
+        .iter()
+        .map(|(amount, cost)| *amount as f64 * cost)
+        .sum();
+
+    gains
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::types::Decimal;
+    use time::OffsetDateTime;
+
+    use super::*;
+
+    fn rates() -> CurrencyRates {
+        serde_json::from_str(
+            r#"{"EUR":0.9,"CNY":7.2,"TRY":27.0,"PLN":4.3,"GBP":0.8,"UAH":36.9,"KRW":1330.0,"BRL":4.9}"#,
+        )
+        .unwrap()
+    }
+
+    fn tx(kind: TransactionKind, amount: i32, price: i64) -> Transaction {
+        Transaction {
+            tx_id: 0,
+            inv_id: 1,
+            steam_id: "76561198000000000".to_string(),
+            kind,
+            amount,
+            price: Decimal::from(price),
+            currency: Currencies::USD,
+            created_at: OffsetDateTime::UNIX_EPOCH,
+        }
+    }
+
+    fn gains(transactions: &[Transaction], method: CostMethod) -> Gains {
+        compute_gains(transactions, method, &rates(), &Currencies::USD)
+    }
+
+    #[test]
+    fn fifo_partial_sell_spans_two_lots() {
+        let gains = gains(
+            &[
+                tx(TransactionKind::Buy, 2, 10),
+                tx(TransactionKind::Buy, 3, 20),
+                tx(TransactionKind::Sell, 4, 30),
+            ],
+            CostMethod::Fifo,
+        );
+
+        assert_eq!(gains.realized, 2.0 * 20.0 + 2.0 * 10.0);
+        assert_eq!(gains.open_amount, 1);
+        assert_eq!(gains.open_cost, 20.0);
+    }
+
+    #[test]
+    fn average_cost_re_averages_after_sell() {
+        let mut transactions = vec![
+            tx(TransactionKind::Buy, 2, 10),
+            tx(TransactionKind::Sell, 1, 20),
+            tx(TransactionKind::Buy, 3, 30),
+        ];
+
+        let re_averaged = gains(&transactions, CostMethod::Average);
+
+        assert_eq!(re_averaged.realized, 10.0);
+        assert_eq!(re_averaged.open_amount, 4);
+        assert_eq!(re_averaged.open_cost, 1.0 * 10.0 + 3.0 * 30.0);
+
+        transactions.push(tx(TransactionKind::Sell, 2, 40));
+
+        let sold = gains(&transactions, CostMethod::Average);
+
+        assert_eq!(sold.realized, 10.0 + 2.0 * (40.0 - 25.0));
+        assert_eq!(sold.open_amount, 2);
+        assert_eq!(sold.open_cost, 50.0);
+    }
+
+    #[test]
+    fn sell_beyond_open_lots_only_matches_what_is_open() {
+        let gains = gains(
+            &[
+                tx(TransactionKind::Buy, 2, 10),
+                tx(TransactionKind::Sell, 5, 15),
+            ],
+            CostMethod::Fifo,
+        );
+
+        assert_eq!(gains.realized, 2.0 * 5.0);
+        assert_eq!(gains.open_amount, 0);
+        assert_eq!(gains.open_cost, 0.0);
+    }
+
+    #[test]
+    fn converts_prices_into_target_currency() {
+        let gains = compute_gains(
+            &[tx(TransactionKind::Buy, 1, 10)],
+            CostMethod::Fifo,
+            &rates(),
+            &Currencies::EUR,
+        );
+
+        assert!((gains.open_cost - 9.0).abs() < 1e-4);
+    }
+}
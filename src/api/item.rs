@@ -0,0 +1,78 @@
+use axum::{
+    extract::{Path, Query, State},
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{target_rates, Conversion, Prices, UnpricedReason},
+    db::{
+        catalog::{get_catalog_item, CatalogItem},
+        investment::Currencies,
+        item::get_items_prices,
+    },
+    error::{Error, Result},
+    state::AppState,
+    steam::market_hash_name::MarketHashName,
+};
+
+pub fn routes() -> Router<AppState> {
+    Router::new().route("/:market_hash_name", get(item_details))
+}
+
+#[derive(Deserialize)]
+struct ItemQuery {
+    currency: Option<Currencies>,
+}
+
+#[derive(Serialize)]
+struct ItemDetails {
+    market_hash_name: String,
+    name: MarketHashName,
+    metadata: Option<CatalogItem>,
+    prices: Option<Prices>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unpriced: Option<UnpricedReason>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    conversion: Option<Conversion>,
+}
+
+/// Catalog metadata and current prices of one item, 404 if neither knows it.
+async fn item_details(
+    Path(market_hash_name): Path<String>,
+    Query(query): Query<ItemQuery>,
+    State(mut state): State<AppState>,
+) -> Result<Json<ItemDetails>> {
+    let metadata = get_catalog_item(&state.pg, &market_hash_name).await?;
+
+    let lookup = get_items_prices(&mut state, std::slice::from_ref(&market_hash_name))
+        .await?
+        .remove(&market_hash_name)
+        .unwrap_or(Err(UnpricedReason::UnknownItem));
+
+    if metadata.is_none() && matches!(lookup, Err(UnpricedReason::UnknownItem)) {
+        return Err(Error::ItemNotFound(market_hash_name));
+    }
+
+    let target = target_rates(&mut state, &query.currency)
+        .await?
+        .zip(query.currency);
+
+    let (prices, unpriced) = match lookup {
+        Ok(prices) => match &target {
+            Some((rates, currency)) => (Some(prices.scaled(rates.rate(currency))), None),
+            _ => (Some(prices), None),
+        },
+        Err(reason) => (None, Some(reason)),
+    };
+
+    Ok(Json(ItemDetails {
+        name: MarketHashName::parse(&market_hash_name),
+        market_hash_name,
+        metadata,
+        prices,
+        unpriced,
+        conversion: target.map(|(rates, currency)| rates.conversion(currency)),
+    }))
+}
@@ -1,143 +1,104 @@
+pub mod admin;
 pub mod investment;
+pub mod item;
+pub mod price;
 pub mod user;
 
-use std::env;
-
 use axum::{
     extract::{Path, Query, State},
     middleware,
     response::{IntoResponse, Redirect},
     routing::{get, post},
-    Extension, Json, Router,
+    Json, Router,
 };
 use http::HeaderMap;
-use redis::{Commands, JsonCommands};
+use redis::{aio::ConnectionManager, AsyncCommands, JsonAsyncCommands};
 use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use time::OffsetDateTime;
 
 use crate::{
-    db::item::{get_item_icon, get_item_prices},
+    db::{
+        catalog::get_item_icon, investment::Currencies, item::get_items_prices,
+        rate::get_currency_rates,
+    },
     error::{Error, Result},
     guard::guard,
-    jwt::User,
+    limit::rate_limit,
     state::AppState,
+    steam::inventory::{self, Inventory},
 };
 
-pub fn routes() -> Router<AppState> {
+pub fn routes(state: AppState) -> Router<AppState> {
+    public_routes(state.clone()).merge(private_routes(state))
+}
+
+/// Read-only market data, open without login behind a per-client rate limit.
+fn public_routes(state: AppState) -> Router<AppState> {
     Router::new()
-        // .route("/inventory", get(get_inventory))
         .route("/inventory/price-check", post(price_check))
-        // .route("/prices", get(get_prices))
         .route("/currencies", get(get_currencies))
         .route("/icon/:market_hash_name", get(get_icon))
-        .nest("/investment", investment::routes())
-        .nest("/user", user::routes())
-        .route_layer(middleware::from_fn(guard))
-}
-
-#[derive(Serialize, Deserialize, Debug)]
-struct Inventory {
-    descriptions: Vec<Description>,
-    total_inventory_count: i32,
+        .route("/investment/suggestion", get(investment::get_suggestion))
+        .nest("/items", item::routes())
+        .nest("/prices", price::routes())
+        .route_layer(middleware::from_fn_with_state(state, rate_limit))
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-struct Description {
-    market_hash_name: String,
-    marketable: i32,
+/// Everything tied to the logged in user.
+fn private_routes(state: AppState) -> Router<AppState> {
+    Router::new()
+        .nest("/investment", investment::routes())
+        .nest("/user", user::routes())
+        .nest("/admin", admin::routes())
+        .route_layer(middleware::from_fn_with_state(state, guard))
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Debug)]
 struct CustomInventory {
     items: Vec<InventoryItem>,
+    unpriced: Vec<UnpricedItem>,
     total_inventory_count: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    conversion: Option<Conversion>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Debug)]
 struct InventoryItem {
     market_hash_name: String,
     prices: Prices,
     count: u32,
 }
 
-async fn get_inventory(
-    State(mut state): State<AppState>,
-    Extension(user): Extension<User>,
-) -> Result<Json<CustomInventory>> {
-    let proxy = reqwest::Proxy::all(env::var("PROXY_URL").unwrap())
-        .map_err(|_| Error::ProxyCreationFail)?;
-
-    let client = reqwest::Client::builder()
-        .proxy(proxy)
-        .build()
-        .map_err(|_| Error::HttpClientCreationFail)?;
-
-    let steam_id = user.steam_id()?;
-
-    let steam_inventory_endpoint =
-        format!("https://steamcommunity.com/inventory/{steam_id}/730/2?l=english&count=1000");
-
+/// Fetches the user's CS inventory, cached for 30 minutes.
+pub async fn fetch_inventory(redis: &mut ConnectionManager, steam_id: &str) -> Result<Inventory> {
     let key = format!("inventory-{steam_id}");
 
-    let cached_inventory: Option<String> = state
-        .redis
+    let cached_inventory: Option<String> = redis
         .json_get(&key, ".")
+        .await
         .map_err(|_| Error::RedisGetFail)?;
 
-    if cached_inventory.is_none() {
-        let new_inventory: Inventory = client
-            .get(steam_inventory_endpoint)
-            .send()
-            .await
-            .map_err(|_| Error::InventoryFetchFail)?
-            .json()
-            .await
-            .map_err(|_| Error::InventoryParseFail)?;
-
-        let items = new_inventory
-            .descriptions
-            .into_iter()
-            .filter(|desc| desc.marketable == 1);
-
-        let mut new_items = vec![];
-
-        for item in items.into_iter() {
-            new_items.push(InventoryItem {
-                prices: get_item_prices(&mut state, &item.market_hash_name).await?,
-                market_hash_name: item.market_hash_name,
-                count: 1,
-            });
-        }
-
-        let custom_inventory = CustomInventory {
-            total_inventory_count: 0,
-            items: new_items,
-        };
-
-        state
-            .redis
-            .json_set(&key, ".", &custom_inventory)
-            .map_err(|_| Error::RedisSetFail)?;
-
-        state
-            .redis
-            .expire(&key, 60 * 30)
-            .map_err(|_| Error::RedisExpireFail)?;
-
-        return Ok(Json(custom_inventory));
+    if let Some(inventory) = cached_inventory {
+        return serde_json::from_str(&inventory).map_err(|_| Error::InventoryParseFail);
     }
 
-    let inventory: CustomInventory =
-        serde_json::from_str(&cached_inventory.unwrap()).map_err(|_| Error::InventoryParseFail)?;
+    let inventory = inventory::fetch_inventory(steam_id).await?;
 
-    Ok(Json(inventory))
-}
+    redis
+        .json_set::<_, _, _, ()>(&key, ".", &inventory)
+        .await
+        .map_err(|_| Error::RedisSetFail)?;
 
-#[derive(Deserialize)]
-struct ItemData {
-    market_hash_name: String,
+    redis
+        .expire::<_, ()>(&key, 60 * 30)
+        .await
+        .map_err(|_| Error::RedisExpireFail)?;
+
+    Ok(inventory)
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SteamPrices {
     pub last_24h: Option<f32>,
     pub last_7d: Option<f32>,
@@ -145,42 +106,113 @@ pub struct SteamPrices {
     pub last_90d: Option<f32>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SkinportPrices {
     pub suggested_price: Option<f32>,
     pub starting_at: Option<f32>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Price {
     pub price: Option<f32>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BuffPrices {
     pub starting_at: Option<Price>,
     pub highest_order: Option<Price>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Prices {
     pub steam: Option<SteamPrices>,
     pub skinport: Option<SkinportPrices>,
     pub buff163: Option<BuffPrices>,
 }
 
-async fn get_prices(
-    Query(query): Query<ItemData>,
-    State(mut state): State<AppState>,
-) -> Result<Json<Prices>> {
-    Ok(Json(
-        get_item_prices(&mut state, &query.market_hash_name).await?,
-    ))
+#[derive(Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum UnpricedReason {
+    UnknownItem,
+    InvalidPrices,
+    NoPrice,
+}
+
+#[derive(Serialize, Debug)]
+pub struct UnpricedItem {
+    pub market_hash_name: String,
+    pub reason: UnpricedReason,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PriceSource {
+    #[default]
+    Steam,
+    Skinport,
+    Buff163,
+}
+
+impl Prices {
+    /// Multiplies every price by `factor`, e.g. an exchange rate.
+    pub fn scaled(self, factor: f32) -> Self {
+        let scale = |price: Option<f32>| price.map(|p| p * factor);
+
+        Self {
+            steam: self.steam.map(|s| SteamPrices {
+                last_24h: scale(s.last_24h),
+                last_7d: scale(s.last_7d),
+                last_30d: scale(s.last_30d),
+                last_90d: scale(s.last_90d),
+            }),
+            skinport: self.skinport.map(|s| SkinportPrices {
+                suggested_price: scale(s.suggested_price),
+                starting_at: scale(s.starting_at),
+            }),
+            buff163: self.buff163.map(|b| BuffPrices {
+                starting_at: b.starting_at.map(|p| Price {
+                    price: scale(p.price),
+                }),
+                highest_order: b.highest_order.map(|p| Price {
+                    price: scale(p.price),
+                }),
+            }),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        [
+            PriceSource::Steam,
+            PriceSource::Skinport,
+            PriceSource::Buff163,
+        ]
+        .into_iter()
+        .all(|source| self.price(source).is_none())
+    }
+
+    /// Current USD price of one unit from `source`, falling back to older steam averages.
+    pub fn price(&self, source: PriceSource) -> Option<f32> {
+        match source {
+            PriceSource::Steam => self
+                .steam
+                .as_ref()
+                .and_then(|s| s.last_24h.or(s.last_7d).or(s.last_30d).or(s.last_90d)),
+            PriceSource::Skinport => self
+                .skinport
+                .as_ref()
+                .and_then(|s| s.starting_at.or(s.suggested_price)),
+            PriceSource::Buff163 => self
+                .buff163
+                .as_ref()
+                .and_then(|b| b.starting_at.as_ref())
+                .and_then(|p| p.price),
+        }
+    }
 }
 
 #[derive(Deserialize, Serialize)]
 #[allow(non_snake_case)]
-struct CurrencyRates {
+pub struct CurrencyRates {
     EUR: f32,
     CNY: f32,
     TRY: f32,
@@ -189,53 +221,73 @@ struct CurrencyRates {
     UAH: f32,
     KRW: f32,
     BRL: f32,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub fetched_at: Option<OffsetDateTime>,
 }
 
-async fn get_currencies(State(mut state): State<AppState>) -> Result<Json<CurrencyRates>> {
-    let cached_rates: Option<String> = state
-        .redis
-        .json_get("currency_rates", ".")
-        .map_err(|_| Error::RedisGetFail)?;
+impl CurrencyRates {
+    /// Units of `currency` per one USD.
+    pub fn rate(&self, currency: &Currencies) -> f32 {
+        match currency {
+            Currencies::USD => 1.0,
+            Currencies::EUR => self.EUR,
+            Currencies::CNY => self.CNY,
+            Currencies::TRY => self.TRY,
+            Currencies::PLN => self.PLN,
+            Currencies::GBP => self.GBP,
+            Currencies::UAH => self.UAH,
+            Currencies::KRW => self.KRW,
+            Currencies::BRL => self.BRL,
+        }
+    }
 
-    if cached_rates.is_none() {
-        let client = reqwest::Client::builder()
-            .gzip(true)
-            .build()
-            .map_err(|_| Error::HttpClientCreationFail)?;
+    pub fn convert(&self, value: f64, from: &Currencies, to: &Currencies) -> f64 {
+        value / self.rate(from) as f64 * self.rate(to) as f64
+    }
 
-        let new_rates: CurrencyRates = client
-            .get("https://prices.csgotrader.app/latest/exchange_rates.json")
-            .send()
-            .await
-            .map_err(|_| Error::RatesFetchFail)?
-            .json()
-            .await
-            .map_err(|_| Error::RatesParseFail)?;
+    pub fn conversion(&self, currency: Currencies) -> Conversion {
+        Conversion {
+            rate: self.rate(&currency),
+            currency,
+            fetched_at: self.fetched_at,
+        }
+    }
+}
 
-        state
-            .redis
-            .json_set("currency_rates", ".", &new_rates)
-            .map_err(|_| Error::RedisSetFail)?;
+/// Target currency of a converted response, echoed back to the client.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Conversion {
+    currency: Currencies,
+    rate: f32,
+    #[serde(with = "time::serde::rfc3339::option")]
+    fetched_at: Option<OffsetDateTime>,
+}
 
-        state
-            .redis
-            .expire("currency_rates", 3600 * 3)
-            .map_err(|_| Error::RedisExpireFail)?;
+#[derive(Deserialize)]
+pub struct CurrencyQuery {
+    pub currency: Option<Currencies>,
+}
 
-        return Ok(Json(new_rates));
+/// Fetches the rates only when a conversion was requested.
+pub async fn target_rates(
+    state: &mut AppState,
+    currency: &Option<Currencies>,
+) -> Result<Option<CurrencyRates>> {
+    match currency {
+        Some(_) => Ok(Some(get_currency_rates(&mut state.redis).await?)),
+        _ => Ok(None),
     }
+}
 
-    let rates: CurrencyRates =
-        serde_json::from_str(&cached_rates.unwrap()).map_err(|_| Error::RatesParseFail)?;
-
-    Ok(Json(rates))
+async fn get_currencies(State(mut state): State<AppState>) -> Result<Json<CurrencyRates>> {
+    Ok(Json(get_currency_rates(&mut state.redis).await?))
 }
 
 async fn get_icon(
     Path(market_hash_name): Path<String>,
-    State(mut state): State<AppState>,
+    State(pg): State<PgPool>,
 ) -> Result<Redirect> {
-    let icon_url = get_item_icon(&mut state.redis, &market_hash_name)
+    let icon_url = get_item_icon(&pg, &market_hash_name)
         .await?
         .ok_or(Error::ItemMissingImage)?;
 
@@ -255,22 +307,52 @@ struct InventoryItems {
 }
 
 async fn price_check(
+    Query(query): Query<CurrencyQuery>,
     State(mut state): State<AppState>,
     Json(body): Json<InventoryItems>,
 ) -> Result<impl IntoResponse> {
+    let rates = target_rates(&mut state, &query.currency).await?;
+    let factor = match (&rates, &query.currency) {
+        (Some(rates), Some(currency)) => rates.rate(currency),
+        _ => 1.0,
+    };
+
+    let names: Vec<String> = body
+        .items
+        .iter()
+        .map(|i| i.market_hash_name.clone())
+        .collect();
+
+    let prices = get_items_prices(&mut state, &names).await?;
+
+    let total_inventory_count = body.items.iter().map(|i| i.count).sum();
+
     let mut new_items = vec![];
+    let mut unpriced = vec![];
 
     for item in body.items.into_iter() {
-        new_items.push(InventoryItem {
-            prices: get_item_prices(&mut state, &item.market_hash_name).await?,
-            market_hash_name: item.market_hash_name,
-            count: item.count,
-        });
+        match prices.get(&item.market_hash_name) {
+            Some(Ok(item_prices)) => new_items.push(InventoryItem {
+                prices: item_prices.clone().scaled(factor),
+                market_hash_name: item.market_hash_name,
+                count: item.count,
+            }),
+            Some(Err(reason)) => unpriced.push(UnpricedItem {
+                market_hash_name: item.market_hash_name,
+                reason: *reason,
+            }),
+            _ => unpriced.push(UnpricedItem {
+                market_hash_name: item.market_hash_name,
+                reason: UnpricedReason::UnknownItem,
+            }),
+        }
     }
 
     let custom_inventory = CustomInventory {
-        total_inventory_count: new_items.iter().map(|i| i.count).sum(),
+        total_inventory_count,
         items: new_items,
+        unpriced,
+        conversion: rates.zip(query.currency).map(|(r, c)| r.conversion(c)),
     };
 
     let mut headers = HeaderMap::new();
@@ -279,3 +361,70 @@ async fn price_check(
 
     Ok((headers, Json(custom_inventory)).into_response())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+    use crate::steam::market_hash_name::NAME_CORPUS;
+
+    /// Percent-encodes every byte but unreserved ones, as clients building icon URLs do.
+    fn encode(name: &str) -> String {
+        name.bytes()
+            .map(|b| match b {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                    (b as char).to_string()
+                }
+                _ => format!("%{b:02X}"),
+            })
+            .collect()
+    }
+
+    #[sqlx::test]
+    async fn icon_redirects_for_corpus_names(pool: PgPool) {
+        let sql = r"
+            insert into item_catalog (market_hash_name, item_type, name, stattrak, souvenir, image)
+            select name, 'skins', name, false, false, 'https://example.com/' || ordinality
+            from unnest($1::varchar[]) with ordinality as name
+        ";
+
+        sqlx::query(sql)
+            .bind(NAME_CORPUS)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let app = Router::new()
+            .route("/icon/:market_hash_name", get(get_icon))
+            .with_state(pool);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(app.into_make_service()),
+        );
+
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+
+        for (i, name) in NAME_CORPUS.iter().enumerate() {
+            let response = client
+                .get(format!("http://{addr}/icon/{}", encode(name)))
+                .send()
+                .await
+                .unwrap();
+
+            assert_eq!(
+                response.headers().get("location").unwrap(),
+                &format!("https://example.com/{}", i + 1),
+                "{name}"
+            );
+        }
+    }
+}
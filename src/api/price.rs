@@ -0,0 +1,86 @@
+use axum::{
+    extract::{Path, Query, State},
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::{
+    api::{target_rates, Conversion, Prices},
+    db::{
+        investment::Currencies,
+        item::get_item_prices,
+        price::{get_price_history, PriceSnapshot},
+    },
+    error::Result,
+    state::AppState,
+};
+
+pub fn routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(get_prices))
+        .route("/:market_hash_name/history", get(price_history))
+}
+
+#[derive(Deserialize)]
+struct ItemData {
+    market_hash_name: String,
+    currency: Option<Currencies>,
+}
+
+#[derive(Serialize)]
+struct ConvertedPrices {
+    #[serde(flatten)]
+    prices: Prices,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    conversion: Option<Conversion>,
+}
+
+async fn get_prices(
+    Query(query): Query<ItemData>,
+    State(mut state): State<AppState>,
+) -> Result<Json<ConvertedPrices>> {
+    let prices = get_item_prices(&mut state, &query.market_hash_name).await?;
+
+    match target_rates(&mut state, &query.currency)
+        .await?
+        .zip(query.currency)
+    {
+        Some((rates, currency)) => Ok(Json(ConvertedPrices {
+            prices: prices.scaled(rates.rate(&currency)),
+            conversion: Some(rates.conversion(currency)),
+        })),
+        _ => Ok(Json(ConvertedPrices {
+            prices,
+            conversion: None,
+        })),
+    }
+}
+
+#[derive(Deserialize)]
+struct HistoryQuery {
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    from: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    to: Option<OffsetDateTime>,
+}
+
+#[derive(Serialize)]
+struct PriceHistory {
+    market_hash_name: String,
+    history: Vec<PriceSnapshot>,
+}
+
+async fn price_history(
+    Path(market_hash_name): Path<String>,
+    Query(query): Query<HistoryQuery>,
+    State(state): State<AppState>,
+) -> Result<Json<PriceHistory>> {
+    let history = get_price_history(&state.pg, &market_hash_name, query.from, query.to).await?;
+
+    Ok(Json(PriceHistory {
+        market_hash_name,
+        history,
+    }))
+}
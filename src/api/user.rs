@@ -1,7 +1,13 @@
-use axum::{extract::State, routing::get, Extension, Router};
+use axum::{
+    extract::{Path, State},
+    routing::{delete, get},
+    Extension, Json, Router,
+};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     db::{
+        api_key::{create_api_key, get_api_keys, revoke_api_key, ApiKey, ApiKeyAuth, ApiKeyScope},
         collection::create_collection,
         user::{create_user, user_exists},
     },
@@ -11,7 +17,10 @@ use crate::{
 };
 
 pub fn routes() -> Router<AppState> {
-    Router::new().route("/", get(get_user))
+    Router::new()
+        .route("/", get(get_user))
+        .route("/api-keys", get(list_api_keys).post(new_api_key))
+        .route("/api-keys/:key_id", delete(delete_api_key))
 }
 
 async fn get_user(State(state): State<AppState>, Extension(user): Extension<User>) -> Result<()> {
@@ -25,3 +34,71 @@ async fn get_user(State(state): State<AppState>, Extension(user): Extension<User
 
     Ok(())
 }
+
+/// Keys can only be created or revoked from a login, not with another key.
+fn require_login(key: Option<Extension<ApiKeyAuth>>) -> Result<()> {
+    match key {
+        Some(_) => Err(Error::AuthKeyNotAllowed),
+        _ => Ok(()),
+    }
+}
+
+#[derive(Serialize)]
+struct ApiKeys {
+    api_keys: Vec<ApiKey>,
+}
+
+async fn list_api_keys(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+) -> Result<Json<ApiKeys>> {
+    let steam_id = user.steam_id()?;
+
+    Ok(Json(ApiKeys {
+        api_keys: get_api_keys(&state.pg, &steam_id).await?,
+    }))
+}
+
+#[derive(Deserialize)]
+struct NewApiKeyReq {
+    name: String,
+    scope: ApiKeyScope,
+}
+
+#[derive(Serialize)]
+struct NewApiKey {
+    #[serde(flatten)]
+    api_key: ApiKey,
+    /// The only time the plain key is returned.
+    key: String,
+}
+
+async fn new_api_key(
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    key: Option<Extension<ApiKeyAuth>>,
+    Json(body): Json<NewApiKeyReq>,
+) -> Result<Json<NewApiKey>> {
+    require_login(key)?;
+
+    let user_id = user.user_id;
+    let steam_id = user.steam_id()?;
+
+    let (api_key, key) =
+        create_api_key(&state.pg, &steam_id, user_id, &body.name, body.scope).await?;
+
+    Ok(Json(NewApiKey { api_key, key }))
+}
+
+async fn delete_api_key(
+    Path(key_id): Path<i32>,
+    State(state): State<AppState>,
+    Extension(user): Extension<User>,
+    key: Option<Extension<ApiKeyAuth>>,
+) -> Result<()> {
+    require_login(key)?;
+
+    let steam_id = user.steam_id()?;
+
+    revoke_api_key(&state.pg, &steam_id, key_id).await
+}
@@ -0,0 +1,226 @@
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{FromRow, PgPool, Type};
+use time::OffsetDateTime;
+
+use crate::{
+    db::pg_error,
+    error::{Error, Result},
+};
+
+/// Every personal API key starts with this, telling it apart from a JWT.
+pub const API_KEY_PREFIX: &str = "cst_";
+
+/// Characters of the key kept in plain text so users can recognise it.
+const SHOWN_PREFIX_LEN: usize = 12;
+
+#[derive(Debug, Type, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[sqlx(type_name = "api_key_scope", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyScope {
+    Read,
+    ReadWrite,
+}
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct ApiKey {
+    pub key_id: i32,
+    pub name: String,
+    pub prefix: String,
+    pub scope: ApiKeyScope,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub last_used_at: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub revoked_at: Option<OffsetDateTime>,
+}
+
+/// Owner and scope of the key a request authenticated with.
+#[derive(Debug, FromRow, Clone)]
+pub struct ApiKeyAuth {
+    pub key_id: i32,
+    pub steam_id: String,
+    pub user_id: i32,
+    pub scope: ApiKeyScope,
+}
+
+fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// Creates a key and returns it along with the plain key, which is not stored.
+pub async fn create_api_key(
+    pool: &PgPool,
+    steam_id: &str,
+    user_id: i32,
+    name: &str,
+    scope: ApiKeyScope,
+) -> Result<(ApiKey, String)> {
+    let mut secret = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut secret);
+
+    let key = format!("{API_KEY_PREFIX}{}", hex::encode(secret));
+
+    let sql = r"
+        insert into api_keys
+        (steam_id, user_id, name, prefix, key_hash, scope) values ($1, $2, $3, $4, $5, $6)
+        returning key_id, name, prefix, scope, created_at, last_used_at, revoked_at
+    ";
+
+    let api_key = sqlx::query_as(sql)
+        .bind(steam_id)
+        .bind(user_id)
+        .bind(name)
+        .bind(&key[..SHOWN_PREFIX_LEN])
+        .bind(hash_key(&key))
+        .bind(scope)
+        .fetch_one(pool)
+        .await
+        .map_err(pg_error(Error::PgInsertFail))?;
+
+    Ok((api_key, key))
+}
+
+pub async fn get_api_keys(pool: &PgPool, steam_id: &str) -> Result<Vec<ApiKey>> {
+    let sql = r"
+        select key_id, name, prefix, scope, created_at, last_used_at, revoked_at
+        from api_keys
+        where steam_id = $1
+        order by key_id asc
+    ";
+
+    sqlx::query_as(sql)
+        .bind(steam_id)
+        .fetch_all(pool)
+        .await
+        .map_err(|_| Error::PgFetchFail)
+}
+
+pub async fn revoke_api_key(pool: &PgPool, steam_id: &str, key_id: i32) -> Result<()> {
+    let sql = r"
+        update api_keys
+        set revoked_at = now()
+        where steam_id = $1 and key_id = $2 and revoked_at is null
+    ";
+
+    let result = sqlx::query(sql)
+        .bind(steam_id)
+        .bind(key_id)
+        .execute(pool)
+        .await
+        .map_err(|_| Error::PgUpdateFail)?;
+
+    if result.rows_affected() == 0 {
+        return Err(Error::PgRowNotFound);
+    }
+
+    Ok(())
+}
+
+/// Resolves a plain key to its owner, recording the use at most once a minute.
+pub async fn authenticate_api_key(pool: &PgPool, key: &str) -> Result<ApiKeyAuth> {
+    let sql = r"
+        select key_id, steam_id, user_id, scope
+        from api_keys
+        where key_hash = $1 and revoked_at is null
+    ";
+
+    let auth: ApiKeyAuth = sqlx::query_as(sql)
+        .bind(hash_key(key))
+        .fetch_optional(pool)
+        .await
+        .map_err(|_| Error::PgFetchFail)?
+        .ok_or(Error::AuthInvalidApiKey)?;
+
+    let sql = r"
+        update api_keys
+        set last_used_at = now()
+        where key_id = $1
+            and (last_used_at is null or last_used_at < now() - interval '1 minute')
+    ";
+
+    // only bookkeeping, a failed update should not reject a valid key
+    if let Err(e) = sqlx::query(sql).bind(auth.key_id).execute(pool).await {
+        println!("ERROR - api key last_used_at {e:?}");
+    }
+
+    Ok(auth)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::user::create_user;
+
+    const OWNER: &str = "76561198000000001";
+    const OTHER: &str = "76561198000000002";
+
+    /// Both users and a read-write key owned by `OWNER`.
+    async fn seed(pool: &PgPool) -> (ApiKey, String) {
+        create_user(pool, OWNER).await.unwrap();
+        create_user(pool, OTHER).await.unwrap();
+
+        create_api_key(pool, OWNER, 1, "script", ApiKeyScope::ReadWrite)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn created_key_authenticates(pool: PgPool) {
+        let (api_key, key) = seed(&pool).await;
+
+        let auth = authenticate_api_key(&pool, &key).await.unwrap();
+
+        assert_eq!(auth.key_id, api_key.key_id);
+        assert_eq!(auth.steam_id, OWNER);
+        assert_eq!(auth.scope, ApiKeyScope::ReadWrite);
+
+        let keys = get_api_keys(&pool, OWNER).await.unwrap();
+        assert!(keys[0].last_used_at.is_some());
+    }
+
+    #[sqlx::test]
+    async fn stores_only_key_hash(pool: PgPool) {
+        let (api_key, key) = seed(&pool).await;
+
+        let sql = r"
+            select prefix, key_hash
+            from api_keys
+            where key_id = $1
+        ";
+
+        let (prefix, key_hash): (String, String) = sqlx::query_as(sql)
+            .bind(api_key.key_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+
+        assert!(key.starts_with(&prefix));
+        assert!(prefix.len() < key.len());
+        assert_eq!(key_hash, hash_key(&key));
+        assert_ne!(key_hash, key);
+    }
+
+    #[sqlx::test]
+    async fn revoked_key_is_invalid(pool: PgPool) {
+        let (api_key, key) = seed(&pool).await;
+
+        revoke_api_key(&pool, OWNER, api_key.key_id).await.unwrap();
+
+        let result = authenticate_api_key(&pool, &key).await;
+
+        assert!(matches!(result, Err(Error::AuthInvalidApiKey)));
+    }
+
+    #[sqlx::test]
+    async fn other_user_cannot_revoke_key(pool: PgPool) {
+        let (api_key, key) = seed(&pool).await;
+
+        let result = revoke_api_key(&pool, OTHER, api_key.key_id).await;
+
+        assert!(matches!(result, Err(Error::PgRowNotFound)));
+        assert!(authenticate_api_key(&pool, &key).await.is_ok());
+    }
+}
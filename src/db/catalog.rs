@@ -0,0 +1,337 @@
+use std::{collections::HashSet, env, path::PathBuf};
+
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{FromRow, PgPool};
+
+use crate::{
+    error::{Error, Result},
+    steam::market_hash_name::MarketHashName,
+};
+
+/// Upstream files imported into the catalog, with the item type stored for their entries.
+const CATALOG_FILES: [(&str, &str); 10] = [
+    ("skins_not_grouped", "skins"),
+    ("stickers", "stickers"),
+    ("crates", "crates"),
+    ("agents", "agents"),
+    ("patches", "patches"),
+    ("graffiti", "graffiti"),
+    ("music_kits", "music_kits"),
+    ("keys", "keys"),
+    ("collectibles", "collectibles"),
+    ("keychains", "keychains"),
+];
+
+const DEFAULT_CATALOG_URL: &str = "https://bymykel.github.io/CSGO-API/api/en";
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct CatalogItem {
+    pub market_hash_name: String,
+    pub item_type: String,
+    pub name: String,
+    pub weapon: Option<String>,
+    pub skin: Option<String>,
+    pub exterior: Option<String>,
+    pub rarity: Option<String>,
+    pub collection: Option<String>,
+    pub min_float: Option<f32>,
+    pub max_float: Option<f32>,
+    pub stattrak: bool,
+    pub souvenir: bool,
+    pub image: Option<String>,
+}
+
+/// Where the importer reads the CSGO-API files from.
+enum CatalogSource {
+    Upstream(String),
+    Dir(PathBuf),
+}
+
+impl CatalogSource {
+    /// `CATALOG_DIR` with local `<file>.json` dumps, otherwise `CATALOG_URL`
+    /// or bymykel's CSGO-API.
+    fn from_env() -> Self {
+        match env::var("CATALOG_DIR") {
+            Ok(dir) => Self::Dir(dir.into()),
+            _ => Self::Upstream(
+                env::var("CATALOG_URL").unwrap_or_else(|_| DEFAULT_CATALOG_URL.to_string()),
+            ),
+        }
+    }
+
+    async fn fetch(&self, file: &str) -> Result<Value> {
+        match self {
+            Self::Upstream(url) => {
+                let client = reqwest::Client::builder()
+                    .gzip(true)
+                    .build()
+                    .map_err(|_| Error::HttpClientCreationFail)?;
+
+                client
+                    .get(format!("{url}/{file}.json"))
+                    .send()
+                    .await
+                    .map_err(|_| Error::ItemsFetchFail)?
+                    .json()
+                    .await
+                    .map_err(|_| Error::ItemsParseFail)
+            }
+            Self::Dir(dir) => {
+                let data = tokio::fs::read(dir.join(format!("{file}.json")))
+                    .await
+                    .map_err(|_| Error::ItemsFetchFail)?;
+
+                serde_json::from_slice(&data).map_err(|_| Error::ItemsParseFail)
+            }
+        }
+    }
+}
+
+/// Reads a field that upstream stores either as a string or as a `{ "name": .. }` object.
+fn text(item: &Value, key: &str) -> Option<String> {
+    let value = item.get(key)?;
+
+    value
+        .get("name")
+        .unwrap_or(value)
+        .as_str()
+        .map(str::to_string)
+}
+
+fn float(item: &Value, key: &str) -> Option<f32> {
+    item.get(key)?.as_f64().map(|f| f as f32)
+}
+
+#[derive(Default)]
+struct CatalogColumns {
+    market_hash_names: Vec<String>,
+    item_types: Vec<String>,
+    names: Vec<String>,
+    weapons: Vec<Option<String>>,
+    skins: Vec<Option<String>>,
+    exteriors: Vec<Option<String>>,
+    rarities: Vec<Option<String>>,
+    collections: Vec<Option<String>>,
+    min_floats: Vec<Option<f32>>,
+    max_floats: Vec<Option<f32>>,
+    stattraks: Vec<bool>,
+    souvenirs: Vec<bool>,
+    images: Vec<Option<String>>,
+}
+
+impl CatalogColumns {
+    fn push(&mut self, item_type: &str, market_hash_name: String, item: &Value) {
+        let name = text(item, "name").unwrap_or_else(|| market_hash_name.clone());
+
+        let parsed = MarketHashName::parse(&market_hash_name);
+        let flag = |key: &str| item.get(key).and_then(Value::as_bool).unwrap_or_default();
+
+        self.stattraks.push(flag("stattrak") || parsed.stattrak);
+        self.souvenirs.push(flag("souvenir") || parsed.souvenir);
+        self.item_types.push(item_type.to_string());
+        self.weapons.push(text(item, "weapon"));
+        self.skins.push(text(item, "pattern"));
+        self.exteriors
+            .push(text(item, "wear").or_else(|| parsed.exterior.map(|e| e.as_str().to_string())));
+        self.rarities.push(text(item, "rarity"));
+        self.collections.push(
+            item.get("collections")
+                .and_then(|c| c.get(0))
+                .and_then(|c| text(c, "name")),
+        );
+        self.min_floats.push(float(item, "min_float"));
+        self.max_floats.push(float(item, "max_float"));
+        self.images.push(text(item, "image"));
+        self.names.push(name);
+        self.market_hash_names.push(market_hash_name);
+    }
+}
+
+/// Imports every catalog file and replaces the catalog in one transaction.
+///
+/// Fails without touching the table if any file cannot be read or nothing was imported.
+pub async fn import_catalog(pool: &PgPool) -> Result<()> {
+    let source = CatalogSource::from_env();
+
+    let mut columns = CatalogColumns::default();
+    let mut seen = HashSet::new();
+
+    for (file, item_type) in CATALOG_FILES {
+        let items = source.fetch(file).await?;
+
+        for item in items.as_array().ok_or(Error::ItemsParseFail)? {
+            let Some(market_hash_name) =
+                text(item, "market_hash_name").or_else(|| text(item, "name"))
+            else {
+                continue;
+            };
+
+            // doppler phases share a market hash name, the first one wins
+            if seen.insert(market_hash_name.clone()) {
+                columns.push(item_type, market_hash_name, item);
+            }
+        }
+    }
+
+    if columns.market_hash_names.is_empty() {
+        return Err(Error::ItemsParseFail);
+    }
+
+    let mut tx = pool.begin().await.map_err(|_| Error::PgUpdateFail)?;
+
+    let sql = r"
+        delete from item_catalog
+        where market_hash_name <> all($1::varchar[])
+    ";
+
+    sqlx::query(sql)
+        .bind(&columns.market_hash_names)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| Error::PgDeleteFail)?;
+
+    let sql = r"
+        insert into item_catalog (
+            market_hash_name, item_type, name, weapon, skin, exterior, rarity,
+            collection, min_float, max_float, stattrak, souvenir, image
+        )
+        select * from unnest(
+            $1::varchar[], $2::varchar[], $3::varchar[], $4::varchar[], $5::varchar[],
+            $6::varchar[], $7::varchar[], $8::varchar[], $9::real[], $10::real[],
+            $11::boolean[], $12::boolean[], $13::varchar[]
+        )
+        on conflict (market_hash_name) do update set
+            item_type = excluded.item_type,
+            name = excluded.name,
+            weapon = excluded.weapon,
+            skin = excluded.skin,
+            exterior = excluded.exterior,
+            rarity = excluded.rarity,
+            collection = excluded.collection,
+            min_float = excluded.min_float,
+            max_float = excluded.max_float,
+            stattrak = excluded.stattrak,
+            souvenir = excluded.souvenir,
+            image = excluded.image
+    ";
+
+    sqlx::query(sql)
+        .bind(columns.market_hash_names)
+        .bind(columns.item_types)
+        .bind(columns.names)
+        .bind(columns.weapons)
+        .bind(columns.skins)
+        .bind(columns.exteriors)
+        .bind(columns.rarities)
+        .bind(columns.collections)
+        .bind(columns.min_floats)
+        .bind(columns.max_floats)
+        .bind(columns.stattraks)
+        .bind(columns.souvenirs)
+        .bind(columns.images)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| {
+            println!("{e}");
+            Error::PgInsertFail
+        })?;
+
+    tx.commit().await.map_err(|_| Error::PgUpdateFail)
+}
+
+pub async fn catalog_is_empty(pool: &PgPool) -> Result<bool> {
+    let sql = r"
+        select not exists(select 1 from item_catalog)
+    ";
+
+    let (empty,): (bool,) = sqlx::query_as(sql)
+        .fetch_one(pool)
+        .await
+        .map_err(|_| Error::PgFetchFail)?;
+
+    Ok(empty)
+}
+
+pub async fn get_catalog_item(
+    pool: &PgPool,
+    market_hash_name: &str,
+) -> Result<Option<CatalogItem>> {
+    let sql = r"
+        select * from item_catalog
+        where market_hash_name = $1
+    ";
+
+    sqlx::query_as(sql)
+        .bind(market_hash_name)
+        .fetch_optional(pool)
+        .await
+        .map_err(|_| Error::PgFetchFail)
+}
+
+/// Icon of the exact item, falling back to any variant of the same weapon and finish
+/// for names the catalog does not list, such as ones with a Doppler phase.
+pub async fn get_item_icon(pool: &PgPool, market_hash_name: &str) -> Result<Option<String>> {
+    if let Some(image) = get_catalog_item(pool, market_hash_name)
+        .await?
+        .and_then(|item| item.image)
+    {
+        return Ok(Some(image));
+    }
+
+    let parsed = MarketHashName::parse(market_hash_name);
+
+    let Some(finish) = parsed.finish else {
+        return Ok(None);
+    };
+
+    let sql = r"
+        select image from item_catalog
+        where weapon = $1 and skin = $2 and image is not null
+        order by stattrak, souvenir
+        limit 1
+    ";
+
+    let image: Option<(String,)> = sqlx::query_as(sql)
+        .bind(parsed.base)
+        .bind(finish)
+        .fetch_optional(pool)
+        .await
+        .map_err(|_| Error::PgFetchFail)?;
+
+    Ok(image.map(|(image,)| image))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::steam::market_hash_name::NAME_CORPUS;
+
+    #[sqlx::test]
+    async fn corpus_names_are_found(pool: PgPool) {
+        let sql = r"
+            insert into item_catalog (market_hash_name, item_type, name, stattrak, souvenir, image)
+            select name, 'skins', name, false, false, 'https://example.com/' || ordinality
+            from unnest($1::varchar[]) with ordinality as name
+        ";
+
+        sqlx::query(sql)
+            .bind(NAME_CORPUS)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        for (i, name) in NAME_CORPUS.iter().enumerate() {
+            let item = get_catalog_item(&pool, name).await.unwrap();
+
+            assert_eq!(
+                item.map(|item| item.market_hash_name).as_deref(),
+                Some(*name)
+            );
+            assert_eq!(
+                get_item_icon(&pool, name).await.unwrap(),
+                Some(format!("https://example.com/{}", i + 1))
+            );
+        }
+    }
+}
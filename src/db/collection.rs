@@ -1,11 +1,14 @@
 use serde::{Deserialize, Serialize};
 use sqlx::{FromRow, PgPool};
 
-use crate::error::{Error, Result};
+use crate::{
+    db::pg_error,
+    error::{Error, Result},
+};
 
 #[derive(Debug, FromRow, Serialize, Deserialize, Clone)]
 pub struct Collection {
-    col_id: i32,
+    pub col_id: i32,
     steam_id: String,
     name: String,
 }
@@ -48,13 +51,17 @@ pub async fn drop_collection(pool: &PgPool, steam_id: String, col_id: i32) -> Re
         where steam_id = $1 and col_id = $2
     ";
 
-    sqlx::query(sql)
+    let result = sqlx::query(sql)
         .bind(steam_id)
         .bind(col_id)
         .execute(pool)
         .await
         .map_err(|_| Error::PgDeleteFail)?;
 
+    if result.rows_affected() == 0 {
+        return Err(Error::PgRowNotFound);
+    }
+
     Ok(())
 }
 
@@ -77,5 +84,21 @@ pub async fn update_collection(
         .bind(col_id)
         .fetch_one(pool)
         .await
-        .map_err(|_| Error::PgUpdateFail)
+        .map_err(pg_error(Error::PgUpdateFail))
+}
+
+pub async fn collection_exists(pool: &PgPool, steam_id: &str, col_id: i32) -> Result<bool> {
+    let sql = r"
+        select * from collections
+        where steam_id = $1 and col_id = $2
+    ";
+
+    let collection = sqlx::query(sql)
+        .bind(steam_id)
+        .bind(col_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|_| Error::PgFetchFail)?;
+
+    Ok(collection.is_some())
 }
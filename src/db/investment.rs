@@ -1,8 +1,13 @@
 use serde::{Deserialize, Serialize};
-use sqlx::{types::Decimal, FromRow, PgPool, Type};
+use sqlx::{types::Decimal, FromRow, PgPool, Postgres, Type};
 
 use crate::{
-    api::investment::EditInvestmentReq,
+    api::{investment::EditInvestmentReq, CurrencyRates},
+    db::{
+        collection::collection_exists,
+        pg_error,
+        transaction::{sync_position, valid_price},
+    },
     error::{Error, Result},
 };
 
@@ -17,7 +22,7 @@ struct Investment {
     currency: Currencies,
 }
 
-#[derive(Debug, Type, Serialize, Deserialize, Clone)]
+#[derive(Debug, Type, Serialize, Deserialize, Clone, PartialEq)]
 #[sqlx(type_name = "currencies")]
 #[allow(non_camel_case_types)]
 pub enum Currencies {
@@ -34,14 +39,28 @@ pub enum Currencies {
 
 #[derive(Debug, FromRow, Serialize, Deserialize, Clone)]
 pub struct CustomInvestment {
-    inv_id: i32,
+    pub inv_id: i32,
     steam_id: String,
     pub item: String,
-    collection: i32,
-    col_name: String,
-    cost: Decimal,
-    amount: i32,
-    currency: Currencies,
+    pub collection: i32,
+    pub col_name: String,
+    pub cost: Decimal,
+    pub amount: i32,
+    pub currency: Currencies,
+}
+
+impl CustomInvestment {
+    /// Re-expresses the cost in `currency`.
+    pub fn convert(&mut self, rates: &CurrencyRates, currency: &Currencies) {
+        let cost = f64::try_from(self.cost).unwrap_or_default();
+        let cost = rates.convert(cost, &self.currency, currency);
+
+        if let Ok(mut cost) = Decimal::try_from(cost) {
+            cost.rescale(2);
+            self.cost = cost;
+            self.currency = currency.clone();
+        }
+    }
 }
 
 pub async fn create_investment(
@@ -53,6 +72,101 @@ pub async fn create_investment(
     amount: i32,
     currency: Currencies,
 ) -> Result<CustomInvestment> {
+    if !collection_exists(pool, &steam_id, col_id).await? {
+        return Err(Error::InvestmentUnknownCollection);
+    }
+
+    let mut tx = pool.begin().await.map_err(|_| Error::PgInsertFail)?;
+
+    let inv_id = insert_investment(
+        &mut tx,
+        &steam_id,
+        market_hash_name,
+        col_id,
+        cost,
+        amount,
+        &currency,
+    )
+    .await?;
+
+    tx.commit().await.map_err(|_| Error::PgInsertFail)?;
+
+    get_investment(pool, &steam_id, inv_id).await
+}
+
+/// Item, cost and amount of an investment opened by [`create_investments`].
+pub struct NewInvestment {
+    pub item: String,
+    pub cost: f32,
+    pub amount: i32,
+}
+
+/// Creates several investments in one collection, all or none of them.
+pub async fn create_investments(
+    pool: &PgPool,
+    steam_id: &str,
+    col_id: i32,
+    investments: Vec<NewInvestment>,
+    currency: Currencies,
+) -> Result<Vec<CustomInvestment>> {
+    if !collection_exists(pool, steam_id, col_id).await? {
+        return Err(Error::InvestmentUnknownCollection);
+    }
+
+    let mut tx = pool.begin().await.map_err(|_| Error::PgInsertFail)?;
+
+    let mut inv_ids = vec![];
+
+    for investment in investments.iter() {
+        inv_ids.push(
+            insert_investment(
+                &mut tx,
+                steam_id,
+                &investment.item,
+                col_id,
+                investment.cost,
+                investment.amount,
+                &currency,
+            )
+            .await?,
+        );
+    }
+
+    tx.commit().await.map_err(|_| Error::PgInsertFail)?;
+
+    let sql = r"
+        select inv.*, c.name as col_name
+        from investments inv inner join collections c on c.col_id = inv.collection
+        where inv.steam_id = $1 and inv.inv_id = any($2)
+        order by inv.inv_id asc
+    ";
+
+    let mut investments: Vec<CustomInvestment> = sqlx::query_as(sql)
+        .bind(steam_id)
+        .bind(inv_ids)
+        .fetch_all(pool)
+        .await
+        .map_err(|_| Error::PgFetchFail)?;
+
+    for investment in investments.iter_mut() {
+        investment.cost.rescale(2);
+    }
+
+    Ok(investments)
+}
+
+/// Inserts an investment together with the buy that opens it, returning its id.
+async fn insert_investment(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    steam_id: &str,
+    market_hash_name: &str,
+    col_id: i32,
+    cost: f32,
+    amount: i32,
+    currency: &Currencies,
+) -> Result<i32> {
+    let cost = valid_price(cost)?;
+
     let sql = r"
         insert into investments
         (steam_id, item, collection, cost, amount, currency) values ($1, $2, $3, $4, $5, $6)
@@ -66,28 +180,48 @@ pub async fn create_investment(
         .bind(cost)
         .bind(amount)
         .bind(currency)
-        .fetch_one(pool)
+        .fetch_one(&mut **tx)
+        .await
+        .map_err(pg_error(Error::PgInsertFail))?;
+
+    let sql = r"
+        insert into transactions
+        (inv_id, steam_id, kind, amount, price, currency) values ($1, $2, 'buy', $3, $4, $5)
+    ";
+
+    sqlx::query(sql)
+        .bind(investment.inv_id)
+        .bind(steam_id)
+        .bind(amount)
+        .bind(cost)
+        .bind(currency)
+        .execute(&mut **tx)
         .await
         .map_err(|e| {
             println!("{e}");
             Error::PgInsertFail
         })?;
 
-    get_investment(pool, investment.inv_id).await
+    Ok(investment.inv_id)
 }
 
-pub async fn get_investment(pool: &PgPool, inv_id: i32) -> Result<CustomInvestment> {
+pub async fn get_investment(
+    pool: &PgPool,
+    steam_id: &str,
+    inv_id: i32,
+) -> Result<CustomInvestment> {
     let sql = r"
         select inv.*, c.name as col_name
         from investments inv inner join collections c on c.col_id = inv.collection
-        where inv.inv_id = $1
+        where inv.steam_id = $1 and inv.inv_id = $2
     ";
 
     let mut invest: CustomInvestment = sqlx::query_as(sql)
+        .bind(steam_id)
         .bind(inv_id)
         .fetch_one(pool)
         .await
-        .map_err(|_| Error::PgFetchFail)?;
+        .map_err(pg_error(Error::PgFetchFail))?;
 
     invest.cost.rescale(2);
 
@@ -124,6 +258,10 @@ pub async fn get_investments_by_coll(
     steam_id: String,
     col_id: i32,
 ) -> Result<Vec<CustomInvestment>> {
+    if !collection_exists(pool, &steam_id, col_id).await? {
+        return Err(Error::PgRowNotFound);
+    }
+
     let sql = r"
         select inv.*, c.name as col_name
         from investments inv inner join collections c on c.col_id = inv.collection
@@ -155,38 +293,234 @@ pub async fn drop_investment(pool: &PgPool, steam_id: String, inv_id: i32) -> Re
         where steam_id = $1 and inv_id = $2
     ";
 
-    sqlx::query(sql)
+    let result = sqlx::query(sql)
         .bind(steam_id)
         .bind(inv_id)
         .execute(pool)
         .await
         .map_err(|_| Error::PgDeleteFail)?;
 
+    if result.rows_affected() == 0 {
+        return Err(Error::PgRowNotFound);
+    }
+
     Ok(())
 }
 
+/// Moves an investment between collections and edits its amount, cost or currency.
+///
+/// Amount and cost follow the transactions ledger, so they can only be edited while the
+/// opening buy is the only transaction, which is then edited along with the investment.
 pub async fn update_investment(
     pool: &PgPool,
     steam_id: String,
     inv_id: i32,
     data: EditInvestmentReq,
 ) -> Result<CustomInvestment> {
+    if !collection_exists(pool, &steam_id, data.col_id).await? {
+        return Err(Error::InvestmentUnknownCollection);
+    }
+
+    let cost = valid_price(data.cost)?;
+
+    let mut tx = pool.begin().await.map_err(|_| Error::PgUpdateFail)?;
+
+    let sql = r"
+        select * from investments
+        where steam_id = $1 and inv_id = $2
+        for update
+    ";
+
+    let investment: Investment = sqlx::query_as(sql)
+        .bind(&steam_id)
+        .bind(inv_id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(pg_error(Error::PgFetchFail))?;
+
+    let position_changed = investment.amount != data.amount
+        || investment.cost.round_dp(2) != cost
+        || investment.currency != data.currency;
+
+    if position_changed {
+        let sql = r"
+            update transactions
+            set amount = $1, price = $2, currency = $3
+            where inv_id = $4 and kind = 'buy'
+                and (select count(*) from transactions where inv_id = $4) = 1
+        ";
+
+        let result = sqlx::query(sql)
+            .bind(data.amount)
+            .bind(cost)
+            .bind(&data.currency)
+            .bind(inv_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|_| Error::PgUpdateFail)?;
+
+        if result.rows_affected() == 0 {
+            return Err(Error::InvestmentHasTransactions);
+        }
+    }
+
     let sql = r"
         update investments
-        set collection = $1, amount = $2, cost = $3, currency = $4
-        where steam_id = $5 and inv_id = $6
+        set collection = $1, currency = $2
+        where steam_id = $3 and inv_id = $4
     ";
 
     sqlx::query(sql)
         .bind(data.col_id)
-        .bind(data.amount)
-        .bind(data.cost)
-        .bind(data.currency)
-        .bind(steam_id)
+        .bind(&data.currency)
+        .bind(&steam_id)
         .bind(inv_id)
-        .execute(pool)
+        .execute(&mut *tx)
+        .await
+        .map_err(pg_error(Error::PgUpdateFail))?;
+
+    sync_position(&mut tx, inv_id).await?;
+
+    tx.commit().await.map_err(|_| Error::PgUpdateFail)?;
+
+    get_investment(pool, &steam_id, inv_id).await
+}
+
+pub async fn get_tracked_items(pool: &PgPool, steam_id: &str, col_id: i32) -> Result<Vec<String>> {
+    let sql = r"
+        select distinct item from investments
+        where steam_id = $1 and collection = $2
+    ";
+
+    let items: Vec<(String,)> = sqlx::query_as(sql)
+        .bind(steam_id)
+        .bind(col_id)
+        .fetch_all(pool)
         .await
-        .map_err(|_| Error::PgUpdateFail)?;
+        .map_err(|_| Error::PgFetchFail)?;
+
+    Ok(items.into_iter().map(|(item,)| item).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{
+        collection::{create_collection, drop_collection},
+        user::create_user,
+    };
+
+    const OWNER: &str = "76561198000000001";
+    const OTHER: &str = "76561198000000002";
+
+    /// Both users, each with a collection, and one investment owned by `OWNER`.
+    async fn seed(pool: &PgPool) -> (i32, i32, CustomInvestment) {
+        create_user(pool, OWNER).await.unwrap();
+        create_user(pool, OTHER).await.unwrap();
+
+        let owner_col = create_collection(pool, OWNER, "owner").await.unwrap();
+        let other_col = create_collection(pool, OTHER, "other").await.unwrap();
+
+        let investment = create_investment(
+            pool,
+            OWNER.to_string(),
+            "AK-47 | Redline (Field-Tested)",
+            owner_col.col_id,
+            12.5,
+            2,
+            Currencies::USD,
+        )
+        .await
+        .unwrap();
+
+        (owner_col.col_id, other_col.col_id, investment)
+    }
+
+    #[sqlx::test]
+    async fn other_user_cannot_get_investment(pool: PgPool) {
+        let (_, _, investment) = seed(&pool).await;
+
+        let result = get_investment(&pool, OTHER, investment.inv_id).await;
+
+        assert!(matches!(result, Err(Error::PgRowNotFound)));
+    }
+
+    #[sqlx::test]
+    async fn other_user_cannot_drop_investment(pool: PgPool) {
+        let (_, _, investment) = seed(&pool).await;
+
+        let result = drop_investment(&pool, OTHER.to_string(), investment.inv_id).await;
+
+        assert!(matches!(result, Err(Error::PgRowNotFound)));
+        assert!(get_investment(&pool, OWNER, investment.inv_id)
+            .await
+            .is_ok());
+    }
+
+    #[sqlx::test]
+    async fn other_user_cannot_update_investment(pool: PgPool) {
+        let (_, other_col, investment) = seed(&pool).await;
+
+        let data = EditInvestmentReq {
+            col_id: other_col,
+            amount: 5,
+            cost: 1.0,
+            currency: Currencies::EUR,
+        };
+
+        let result = update_investment(&pool, OTHER.to_string(), investment.inv_id, data).await;
+
+        assert!(matches!(result, Err(Error::PgRowNotFound)));
+
+        let unchanged = get_investment(&pool, OWNER, investment.inv_id)
+            .await
+            .unwrap();
+
+        assert_eq!(unchanged.amount, 2);
+        assert_eq!(unchanged.currency, Currencies::USD);
+    }
+
+    #[sqlx::test]
+    async fn other_user_cannot_drop_collection(pool: PgPool) {
+        let (owner_col, _, _) = seed(&pool).await;
+
+        let result = drop_collection(&pool, OTHER.to_string(), owner_col).await;
+
+        assert!(matches!(result, Err(Error::PgRowNotFound)));
+        assert!(collection_exists(&pool, OWNER, owner_col).await.unwrap());
+    }
+
+    #[sqlx::test]
+    async fn other_user_cannot_invest_into_collection(pool: PgPool) {
+        let (owner_col, _, _) = seed(&pool).await;
+
+        let result = create_investment(
+            &pool,
+            OTHER.to_string(),
+            "Prisma 2 Case",
+            owner_col,
+            1.0,
+            1,
+            Currencies::USD,
+        )
+        .await;
+
+        assert!(matches!(result, Err(Error::InvestmentUnknownCollection)));
+    }
+
+    #[sqlx::test]
+    async fn other_user_cannot_list_collection(pool: PgPool) {
+        let (owner_col, _, _) = seed(&pool).await;
+
+        let result = get_investments_by_coll(&pool, OTHER.to_string(), owner_col).await;
 
-    get_investment(pool, inv_id).await
+        assert!(matches!(result, Err(Error::PgRowNotFound)));
+        assert_eq!(
+            get_investments_by_coll(&pool, OWNER.to_string(), owner_col)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
 }
@@ -1,181 +1,159 @@
-use redis::{Commands, JsonCommands};
+use std::collections::HashMap;
+
 use serde::Serialize;
-use serde_json::Value;
-use sqlx::{prelude::FromRow, Postgres, QueryBuilder};
+use sqlx::{prelude::FromRow, PgPool};
 
 use crate::{
-    api::Prices,
+    api::{investment::SuggestionReq, Prices, UnpricedReason},
+    db::snapshot::local_prices,
     error::{Error, Result},
     state::AppState,
 };
 
-pub async fn get_item_icon(
-    redis: &mut redis::Client,
-    market_hash_name: &str,
-) -> Result<Option<String>> {
-    let item_types = [
-        "skins",
-        "stickers",
-        "crates",
-        "agents",
-        "patches",
-        "graffiti",
-        "music_kits",
-    ];
-
-    for item_type in item_types.iter() {
-        match get_icon(redis, item_type, market_hash_name).await? {
-            Some(icon) => return Ok(Some(icon)),
-            _ => (),
-        }
-    }
+pub async fn item_exists(state: &mut AppState, market_hash_name: &str) -> Result<Option<()>> {
+    let prices = local_prices(state).await?;
 
-    Ok(None)
+    Ok(prices.contains(market_hash_name).then_some(()))
 }
 
-async fn get_icon(
-    redis: &mut redis::Client,
-    item_type: &str,
-    market_hash_name: &str,
-) -> Result<Option<String>> {
-    let item = get_item_object(redis, item_type, market_hash_name).await?;
-
-    match item {
-        Some(json) => {
-            if json == "[]" {
-                return Ok(None);
-            }
-
-            let item: Value = serde_json::from_str(&json).map_err(|_| Error::ItemsParseFail)?;
-
-            Ok(Some(
-                item.get(0)
-                    .ok_or(Error::ItemMissingImage)?
-                    .get("image")
-                    .ok_or(Error::ItemMissingImage)?
-                    .as_str()
-                    .ok_or(Error::ItemMissingImage)?
-                    .to_string(),
-            ))
-        }
-        _ => Ok(None),
-    }
-}
+pub async fn get_item_prices(state: &mut AppState, market_hash_name: &str) -> Result<Prices> {
+    let prices = local_prices(state).await?;
 
-pub async fn item_exists(state: &mut AppState, market_hash_name: &str) -> Result<Option<()>> {
-    match get_price_object(state, market_hash_name).await? {
-        Some(json) => {
-            if json == "[]" {
-                return Ok(None);
-            }
-
-            Ok(Some(()))
-        }
-        _ => Ok(None),
+    match prices.get(market_hash_name) {
+        Some(Some(item)) => Ok(item.clone()),
+        Some(None) => Err(Error::PricesParseFail),
+        None => Err(Error::InvalidHashName(market_hash_name.to_string())),
     }
 }
 
-pub async fn get_item_prices(state: &mut AppState, market_hash_name: &str) -> Result<Prices> {
-    let prices = get_price_object(state, market_hash_name)
-        .await?
-        .ok_or(Error::PricesFetchFail)?;
+pub type PriceLookup = core::result::Result<Prices, UnpricedReason>;
+
+/// Looks up many items in the local snapshot.
+///
+/// Every requested name is in the map, items that cannot be priced carry the reason.
+pub async fn get_items_prices(
+    state: &mut AppState,
+    market_hash_names: &[String],
+) -> Result<HashMap<String, PriceLookup>> {
+    let snapshot = local_prices(state).await?;
+
+    let mut prices = HashMap::new();
 
-    let mut prices_value: Value =
-        serde_json::from_str(&prices).map_err(|_| Error::PricesParseFail)?;
+    for name in market_hash_names.iter() {
+        let item = match snapshot.get(name) {
+            Some(Some(item)) if item.is_empty() => Err(UnpricedReason::NoPrice),
+            Some(Some(item)) => Ok(item.clone()),
+            Some(None) => Err(UnpricedReason::InvalidPrices),
+            None => Err(UnpricedReason::UnknownItem),
+        };
 
-    let prices = prices_value
-        .get_mut(0)
-        .ok_or(Error::PricesFetchFail)?
-        .take();
+        prices.insert(name.clone(), item);
+    }
 
-    serde_json::from_value(prices).map_err(|_| Error::PricesParseFail)
+    Ok(prices)
 }
 
-async fn get_price_object(state: &mut AppState, market_hash_name: &str) -> Result<Option<String>> {
-    let cached_prices: Option<String> = state
-        .redis
-        .json_get("csgotrader_prices", format!("$[\"{}\"]", market_hash_name))
-        .map_err(|_| Error::RedisGetFail)?;
-
-    if cached_prices.is_none() {
-        let client = reqwest::Client::builder()
-            .gzip(true)
-            .build()
-            .map_err(|_| Error::HttpClientCreationFail)?;
-
-        let new_prices: Value = client
-            .get("https://prices.csgotrader.app/latest/prices_v6.json")
-            .send()
-            .await
-            .map_err(|_| Error::PricesFetchFail)?
-            .json()
-            .await
-            .map_err(|_| Error::PricesParseFail)?;
-
-        state
-            .redis
-            .json_set("csgotrader_prices", ".", &new_prices)
-            .map_err(|_| Error::RedisSetFail)?;
-
-        state
-            .redis
-            .expire("csgotrader_prices", 3600 * 8)
-            .map_err(|_| Error::RedisExpireFail)?;
-
-        let sql = r"
-            delete from items
-        ";
-
-        sqlx::query(sql)
-            .execute(&state.pg)
-            .await
-            .map_err(|_| Error::PgDeleteFail)?;
-
-        if let Some(prices_object) = new_prices.as_object() {
-            let mut query_builder: QueryBuilder<Postgres> =
-                QueryBuilder::new("insert into items(market_hash_name) ");
-
-            let keys = prices_object.keys();
-
-            query_builder.push_values(keys, |mut b, key| {
-                b.push_bind(key);
-            });
-
-            let query = query_builder.build();
-
-            query
-                .execute(&state.pg)
-                .await
-                .map_err(|_| Error::PgInsertFail)?;
-        }
+/// Brings the suggestion table in line with `names` in one transaction,
+/// only touching names that were added or removed.
+///
+/// An empty list is treated as a bad snapshot and leaves the table untouched.
+pub async fn sync_items(pool: &PgPool, names: Vec<String>) -> Result<()> {
+    if names.is_empty() {
+        return Ok(());
     }
 
-    state
-        .redis
-        .json_get("csgotrader_prices", format!("$[\"{}\"]", market_hash_name))
-        .map_err(|_| Error::RedisGetFail)
+    let mut tx = pool.begin().await.map_err(|_| Error::PgUpdateFail)?;
+
+    let sql = r"
+        delete from items
+        where market_hash_name <> all($1::varchar[])
+    ";
+
+    sqlx::query(sql)
+        .bind(&names)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| Error::PgDeleteFail)?;
+
+    let sql = r"
+        insert into items (market_hash_name)
+        select unnest($1::varchar[])
+        on conflict do nothing
+    ";
+
+    sqlx::query(sql)
+        .bind(&names)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| Error::PgInsertFail)?;
+
+    tx.commit().await.map_err(|_| Error::PgUpdateFail)
 }
 
+/// Most suggestions a single request can ask for.
+const MAX_SUGGESTIONS: i64 = 50;
+
 #[derive(FromRow, Debug, Serialize)]
 pub struct Item {
     pub market_hash_name: String,
+    pub item_type: String,
+    pub exterior: Option<String>,
+    pub stattrak: bool,
+    pub souvenir: bool,
+    pub icon: Option<String>,
 }
 
-pub async fn suggest_items(sqlite: &sqlx::PgPool, item_name: String) -> Result<Vec<Item>> {
+/// Priced items matching every word of the query or close to it by trigram
+/// similarity, best matches first.
+///
+/// Details come from the catalog, items it does not list yet are still suggested
+/// with the type `other` and the exterior and flags their name spells out.
+pub async fn suggest_items(pool: &PgPool, query: SuggestionReq) -> Result<Vec<Item>> {
+    let patterns: Vec<String> = query
+        .q
+        .split_whitespace()
+        .map(|word| {
+            let word = word
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+
+            format!("%{word}%")
+        })
+        .collect();
+
     let sql = r"
-        with search as (
-            select to_tsquery(string_agg(lexeme || ':*', ' & ' order by positions)) as query
-            from unnest(to_tsvector($1))
-        )
-        select items.*
-        from items, search
-        where (items.market_hash_name @@ search.query)
-        limit 5
+        select * from (
+            select i.market_hash_name,
+                coalesce(c.item_type, 'other') as item_type,
+                coalesce(
+                    c.exterior,
+                    substring(i.market_hash_name
+                        from '\((Factory New|Minimal Wear|Field-Tested|Well-Worn|Battle-Scarred)\)')
+                ) as exterior,
+                coalesce(c.stattrak, i.market_hash_name like '%StatTrak™%') as stattrak,
+                coalesce(c.souvenir, i.market_hash_name like 'Souvenir %') as souvenir,
+                c.image as icon
+            from items i
+            left join item_catalog c on c.market_hash_name = i.market_hash_name
+            where i.market_hash_name ilike all($2::text[]) or $1 <% i.market_hash_name
+        ) s
+        where ($3::varchar is null or s.item_type = $3)
+            and ($4::boolean is null or s.stattrak = $4)
+            and ($5::varchar is null or s.exterior = $5)
+        order by word_similarity($1, s.market_hash_name) desc,
+            length(s.market_hash_name), s.market_hash_name
+        limit $6
     ";
 
     sqlx::query_as(sql)
-        .bind(item_name)
-        .fetch_all(sqlite)
+        .bind(&query.q)
+        .bind(patterns)
+        .bind(query.item_type)
+        .bind(query.stattrak)
+        .bind(query.exterior.map(|e| e.as_str()))
+        .bind(query.limit.unwrap_or(5).clamp(1, MAX_SUGGESTIONS))
+        .fetch_all(pool)
         .await
         .map_err(|e| {
             println!("{e:?}");
@@ -183,84 +161,115 @@ pub async fn suggest_items(sqlite: &sqlx::PgPool, item_name: String) -> Result<V
         })
 }
 
-async fn get_item_object(
-    redis: &mut redis::Client,
-    item_type: &str,
-    market_hash_name: &str,
-) -> Result<Option<String>> {
-    let key = format!("cs_{item_type}");
-    let mut path = format!("$.[?@.name==\"{market_hash_name}\"]");
-
-    match item_type {
-        "skins" | "graffiti" => match market_hash_name.rfind("(") {
-            Some(pos) => {
-                path = format!(
-                    "$.[?@.name==\"{}\"]",
-                    &market_hash_name[..pos - 1]
-                        .replace("StatTrak™ ", "")
-                        .replace("Souvenir ", "")
-                );
-            }
-            _ => (),
-        },
-        "music_kits" => {
-            path = format!(
-                "$.[?@.name==\"{}\"]",
-                &market_hash_name.replace("StatTrak™ ", "")
-            );
-        }
-
-        _ => (),
-    };
-
-    if item_type == "music_kits" {
-        path = format!(
-            "$.[?@.name==\"{}\"]",
-            &market_hash_name.replace("StatTrak™ ", "")
-        );
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::steam::market_hash_name::Exterior;
 
-    let items: Option<String> = redis.json_get(&key, ".").map_err(|_| Error::RedisGetFail)?;
+    async fn item_names(pool: &PgPool) -> Vec<String> {
+        let names: Vec<(String,)> =
+            sqlx::query_as("select market_hash_name from items order by market_hash_name")
+                .fetch_all(pool)
+                .await
+                .unwrap();
 
-    if items.is_none() {
-        set_items(redis, item_type).await?;
+        names.into_iter().map(|(name,)| name).collect()
     }
 
-    redis.json_get(&key, &path).map_err(|_| Error::RedisGetFail)
-}
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
 
-async fn set_items(redis: &mut redis::Client, item_type: &str) -> Result<()> {
-    let new_skins = get_items(item_type).await?;
+    #[sqlx::test]
+    async fn failed_insert_keeps_old_names(pool: PgPool) {
+        sync_items(
+            &pool,
+            names(&["AK-47 | Redline (Field-Tested)", "Prisma 2 Case"]),
+        )
+        .await
+        .unwrap();
 
-    let key = format!("cs_{item_type}");
+        // longer than the column allows, failing the insert after the delete ran
+        let too_long = "★".repeat(129);
 
-    redis
-        .json_set(&key, ".", &new_skins)
-        .map_err(|_| Error::RedisSetFail)?;
+        let result = sync_items(&pool, names(&["Recoil Case", &too_long])).await;
 
-    redis
-        .expire(&key, 3600 * 24)
-        .map_err(|_| Error::RedisExpireFail)?;
+        assert!(matches!(result, Err(Error::PgInsertFail)));
+        assert_eq!(
+            item_names(&pool).await,
+            names(&["AK-47 | Redline (Field-Tested)", "Prisma 2 Case"])
+        );
+    }
 
-    Ok(())
-}
+    #[sqlx::test]
+    async fn empty_snapshot_leaves_table_untouched(pool: PgPool) {
+        sync_items(
+            &pool,
+            names(&["AK-47 | Redline (Field-Tested)", "Prisma 2 Case"]),
+        )
+        .await
+        .unwrap();
 
-async fn get_items(item_type: &str) -> Result<Value> {
-    let client = reqwest::Client::builder()
-        .gzip(true)
-        .build()
-        .map_err(|_| Error::HttpClientCreationFail)?;
+        sync_items(&pool, vec![]).await.unwrap();
 
-    let url = "https://bymykel.github.io/CSGO-API/api/en/";
+        assert_eq!(
+            item_names(&pool).await,
+            names(&["AK-47 | Redline (Field-Tested)", "Prisma 2 Case"])
+        );
+    }
 
-    let new_items: Value = client
-        .get(format!("{url}/{item_type}.json"))
-        .send()
+    #[sqlx::test]
+    async fn suggests_items_missing_from_catalog(pool: PgPool) {
+        sync_items(
+            &pool,
+            names(&["StatTrak™ Music Kit | Noisia, Sharpened", "Prisma 2 Case"]),
+        )
         .await
-        .map_err(|_| Error::ItemsFetchFail)?
-        .json()
+        .unwrap();
+
+        let query = SuggestionReq {
+            q: "noisia".to_string(),
+            limit: None,
+            item_type: None,
+            stattrak: Some(true),
+            exterior: None,
+        };
+
+        let items = suggest_items(&pool, query).await.unwrap();
+
+        assert_eq!(items.len(), 1);
+        assert_eq!(
+            items[0].market_hash_name,
+            "StatTrak™ Music Kit | Noisia, Sharpened"
+        );
+        assert_eq!(items[0].item_type, "other");
+        assert!(items[0].stattrak && items[0].icon.is_none());
+    }
+
+    #[sqlx::test]
+    async fn filters_items_missing_from_catalog_by_exterior(pool: PgPool) {
+        sync_items(
+            &pool,
+            names(&[
+                "AK-47 | Redline (Field-Tested)",
+                "AK-47 | Redline (Minimal Wear)",
+            ]),
+        )
         .await
-        .map_err(|_| Error::ItemsParseFail)?;
+        .unwrap();
 
-    Ok(new_items)
+        let query = SuggestionReq {
+            q: "redline".to_string(),
+            limit: None,
+            item_type: None,
+            stattrak: None,
+            exterior: Some(Exterior::FieldTested),
+        };
+
+        let items = suggest_items(&pool, query).await.unwrap();
+
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].market_hash_name, "AK-47 | Redline (Field-Tested)");
+        assert_eq!(items[0].exterior.as_deref(), Some("Field-Tested"));
+    }
 }
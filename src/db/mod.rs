@@ -1,4 +1,27 @@
+pub mod api_key;
+pub mod catalog;
 pub mod collection;
 pub mod investment;
 pub mod item;
+pub mod price;
+pub mod rate;
+pub mod refresh;
+pub mod snapshot;
+pub mod transaction;
 pub mod user;
+
+use crate::error::Error;
+
+/// Maps a missing row or an unknown collection to its own error, anything else to `fail`.
+pub fn pg_error(fail: Error) -> impl FnOnce(sqlx::Error) -> Error {
+    move |e| match e {
+        sqlx::Error::RowNotFound => Error::PgRowNotFound,
+        sqlx::Error::Database(e) if e.constraint() == Some("fk_collection_inv") => {
+            Error::InvestmentUnknownCollection
+        }
+        e => {
+            println!("{e}");
+            fail
+        }
+    }
+}
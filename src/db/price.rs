@@ -0,0 +1,225 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{FromRow, PgPool};
+use time::OffsetDateTime;
+
+use crate::{
+    api::Prices,
+    error::{Error, Result},
+};
+
+#[derive(Debug, FromRow, Serialize)]
+pub struct PriceSnapshot {
+    #[serde(with = "time::serde::rfc3339")]
+    recorded_at: OffsetDateTime,
+    steam_last_24h: Option<f32>,
+    steam_last_7d: Option<f32>,
+    steam_last_30d: Option<f32>,
+    steam_last_90d: Option<f32>,
+    skinport_suggested: Option<f32>,
+    skinport_starting_at: Option<f32>,
+    buff163_starting_at: Option<f32>,
+    buff163_highest_order: Option<f32>,
+}
+
+#[derive(Default)]
+struct HistoryColumns {
+    names: Vec<String>,
+    steam_last_24h: Vec<Option<f32>>,
+    steam_last_7d: Vec<Option<f32>>,
+    steam_last_30d: Vec<Option<f32>>,
+    steam_last_90d: Vec<Option<f32>>,
+    skinport_suggested: Vec<Option<f32>>,
+    skinport_starting_at: Vec<Option<f32>>,
+    buff163_starting_at: Vec<Option<f32>>,
+    buff163_highest_order: Vec<Option<f32>>,
+}
+
+impl HistoryColumns {
+    fn push(&mut self, market_hash_name: &str, prices: Prices) {
+        let steam = prices.steam.as_ref();
+        let skinport = prices.skinport.as_ref();
+        let buff = prices.buff163.as_ref();
+
+        self.names.push(market_hash_name.to_string());
+        self.steam_last_24h.push(steam.and_then(|s| s.last_24h));
+        self.steam_last_7d.push(steam.and_then(|s| s.last_7d));
+        self.steam_last_30d.push(steam.and_then(|s| s.last_30d));
+        self.steam_last_90d.push(steam.and_then(|s| s.last_90d));
+        self.skinport_suggested
+            .push(skinport.and_then(|s| s.suggested_price));
+        self.skinport_starting_at
+            .push(skinport.and_then(|s| s.starting_at));
+        self.buff163_starting_at.push(
+            buff.and_then(|b| b.starting_at.as_ref())
+                .and_then(|p| p.price),
+        );
+        self.buff163_highest_order.push(
+            buff.and_then(|b| b.highest_order.as_ref())
+                .and_then(|p| p.price),
+        );
+    }
+}
+
+/// Stores one dated row per item of a freshly fetched price snapshot.
+pub async fn insert_price_history(pool: &PgPool, prices: &Value) -> Result<()> {
+    let Some(prices_object) = prices.as_object() else {
+        return Ok(());
+    };
+
+    let mut columns = HistoryColumns::default();
+
+    for (market_hash_name, item) in prices_object.iter() {
+        if let Ok(prices) = Prices::deserialize(item) {
+            columns.push(market_hash_name, prices);
+        }
+    }
+
+    let sql = r"
+        insert into price_history (
+            market_hash_name, recorded_at,
+            steam_last_24h, steam_last_7d, steam_last_30d, steam_last_90d,
+            skinport_suggested, skinport_starting_at,
+            buff163_starting_at, buff163_highest_order
+        )
+        select name, now(), s24, s7, s30, s90, sps, spa, bsa, bho
+        from unnest(
+            $1::varchar[], $2::real[], $3::real[], $4::real[], $5::real[],
+            $6::real[], $7::real[], $8::real[], $9::real[]
+        ) as t(name, s24, s7, s30, s90, sps, spa, bsa, bho)
+        on conflict do nothing
+    ";
+
+    sqlx::query(sql)
+        .bind(columns.names)
+        .bind(columns.steam_last_24h)
+        .bind(columns.steam_last_7d)
+        .bind(columns.steam_last_30d)
+        .bind(columns.steam_last_90d)
+        .bind(columns.skinport_suggested)
+        .bind(columns.skinport_starting_at)
+        .bind(columns.buff163_starting_at)
+        .bind(columns.buff163_highest_order)
+        .execute(pool)
+        .await
+        .map_err(|e| {
+            println!("{e}");
+            Error::PgInsertFail
+        })?;
+
+    Ok(())
+}
+
+pub async fn get_price_history(
+    pool: &PgPool,
+    market_hash_name: &str,
+    from: Option<OffsetDateTime>,
+    to: Option<OffsetDateTime>,
+) -> Result<Vec<PriceSnapshot>> {
+    let sql = r"
+        select recorded_at,
+            steam_last_24h, steam_last_7d, steam_last_30d, steam_last_90d,
+            skinport_suggested, skinport_starting_at,
+            buff163_starting_at, buff163_highest_order
+        from price_history
+        where market_hash_name = $1
+            and ($2::timestamptz is null or recorded_at >= $2)
+            and ($3::timestamptz is null or recorded_at <= $3)
+        order by recorded_at asc
+    ";
+
+    sqlx::query_as(sql)
+        .bind(market_hash_name)
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await
+        .map_err(|_| Error::PgFetchFail)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use time::Duration;
+
+    use super::*;
+
+    const REDLINE: &str = "AK-47 | Redline (Field-Tested)";
+
+    fn at(unix: i64) -> OffsetDateTime {
+        OffsetDateTime::from_unix_timestamp(unix).unwrap()
+    }
+
+    #[sqlx::test]
+    async fn stores_one_row_per_parsable_item(pool: PgPool) {
+        let prices = json!({
+            REDLINE: {
+                "steam": { "last_24h": 12.5, "last_7d": 12.0 },
+                "skinport": { "suggested_price": 11.0, "starting_at": 10.5 },
+                "buff163": { "starting_at": { "price": 9.5 }, "highest_order": null }
+            },
+            "Prisma 2 Case": { "steam": "not prices" }
+        });
+
+        insert_price_history(&pool, &prices).await.unwrap();
+
+        let history = get_price_history(&pool, REDLINE, None, None).await.unwrap();
+
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].steam_last_24h, Some(12.5));
+        assert_eq!(history[0].steam_last_30d, None);
+        assert_eq!(history[0].skinport_starting_at, Some(10.5));
+        assert_eq!(history[0].buff163_starting_at, Some(9.5));
+        assert_eq!(history[0].buff163_highest_order, None);
+
+        assert!(get_price_history(&pool, "Prisma 2 Case", None, None)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[sqlx::test]
+    async fn filters_history_by_from_and_to(pool: PgPool) {
+        let day = Duration::days(1);
+        let start = at(1_700_000_000);
+
+        for (i, recorded_at) in [start, start + day, start + day * 2]
+            .into_iter()
+            .enumerate()
+        {
+            sqlx::query(
+                "insert into price_history (market_hash_name, recorded_at, steam_last_24h) \
+                 values ($1, $2, $3)",
+            )
+            .bind(REDLINE)
+            .bind(recorded_at)
+            .bind(i as f32)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let recorded = |history: Vec<PriceSnapshot>| {
+            history
+                .into_iter()
+                .map(|snapshot| snapshot.recorded_at)
+                .collect::<Vec<_>>()
+        };
+
+        let all = get_price_history(&pool, REDLINE, None, None).await.unwrap();
+        let from = get_price_history(&pool, REDLINE, Some(start + day), None)
+            .await
+            .unwrap();
+        let to = get_price_history(&pool, REDLINE, None, Some(start + day))
+            .await
+            .unwrap();
+        let between = get_price_history(&pool, REDLINE, Some(start + day), Some(start + day))
+            .await
+            .unwrap();
+
+        assert_eq!(recorded(all), [start, start + day, start + day * 2]);
+        assert_eq!(recorded(from), [start + day, start + day * 2]);
+        assert_eq!(recorded(to), [start, start + day]);
+        assert_eq!(recorded(between), [start + day]);
+    }
+}
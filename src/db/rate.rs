@@ -0,0 +1,50 @@
+use redis::{aio::ConnectionManager, AsyncCommands, JsonAsyncCommands};
+use time::OffsetDateTime;
+
+use crate::{
+    api::CurrencyRates,
+    error::{Error, Result},
+};
+
+pub async fn get_currency_rates(redis: &mut ConnectionManager) -> Result<CurrencyRates> {
+    let cached_rates: Option<String> = redis
+        .json_get("currency_rates", ".")
+        .await
+        .map_err(|_| Error::RedisGetFail)?;
+
+    if let Some(rates) = cached_rates {
+        return serde_json::from_str(&rates).map_err(|_| Error::RatesParseFail);
+    }
+
+    refresh_currency_rates(redis).await
+}
+
+pub async fn refresh_currency_rates(redis: &mut ConnectionManager) -> Result<CurrencyRates> {
+    let client = reqwest::Client::builder()
+        .gzip(true)
+        .build()
+        .map_err(|_| Error::HttpClientCreationFail)?;
+
+    let mut new_rates: CurrencyRates = client
+        .get("https://prices.csgotrader.app/latest/exchange_rates.json")
+        .send()
+        .await
+        .map_err(|_| Error::RatesFetchFail)?
+        .json()
+        .await
+        .map_err(|_| Error::RatesParseFail)?;
+
+    new_rates.fetched_at = Some(OffsetDateTime::now_utc());
+
+    redis
+        .json_set::<_, _, _, ()>("currency_rates", ".", &new_rates)
+        .await
+        .map_err(|_| Error::RedisSetFail)?;
+
+    redis
+        .expire::<_, ()>("currency_rates", 3600 * 3)
+        .await
+        .map_err(|_| Error::RedisExpireFail)?;
+
+    Ok(new_rates)
+}
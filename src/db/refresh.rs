@@ -0,0 +1,228 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use redis::{aio::ConnectionManager, AsyncCommands, JsonAsyncCommands, Script};
+
+use crate::{
+    db::{item::sync_items, price::insert_price_history},
+    error::{Error, Result},
+    state::AppState,
+};
+
+pub const PRICES_KEY: &str = "csgotrader_prices";
+/// Changes on every stored snapshot so instances know to reload their local copy.
+pub const PRICES_VERSION_KEY: &str = "csgotrader_prices_version";
+const PRICES_FRESH_KEY: &str = "csgotrader_prices_fresh";
+const PRICES_LOCK_KEY: &str = "csgotrader_prices_lock";
+
+/// How long a snapshot is served without triggering a refresh.
+pub const FRESH_SECS: usize = 3600 * 8;
+/// How long a stale snapshot is kept around while refreshes keep failing.
+const STALE_SECS: usize = 3600 * 24 * 7;
+const LOCK_SECS: usize = 120;
+/// How often the holder of the lock extends it while the refresh runs.
+const LOCK_RENEW_INTERVAL: Duration = Duration::from_secs(LOCK_SECS as u64 / 3);
+
+/// Makes sure a price snapshot is in Redis.
+///
+/// A stale snapshot keeps being served while one background task refreshes it,
+/// only a missing snapshot makes the caller wait for the download.
+pub async fn ensure_prices(state: &mut AppState) -> Result<()> {
+    let exists: bool = state
+        .redis
+        .exists(PRICES_KEY)
+        .await
+        .map_err(|_| Error::RedisGetFail)?;
+
+    if !exists {
+        return refresh_prices(state).await;
+    }
+
+    if prices_fresh(&mut state.redis).await? {
+        return Ok(());
+    }
+
+    if let Ok(guard) = state.price_refresh.clone().try_lock_owned() {
+        let mut state = state.clone();
+
+        tokio::spawn(async move {
+            let _guard = guard;
+
+            if let Err(e) = refresh_unlocked(&mut state).await {
+                println!("ERROR - background price refresh {e:?}");
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// Refreshes the snapshot unless it is already fresh, coalescing concurrent
+/// callers in this process and across instances.
+pub async fn refresh_prices(state: &mut AppState) -> Result<()> {
+    let _guard = state.price_refresh.clone().lock_owned().await;
+
+    if prices_fresh(&mut state.redis).await? {
+        return Ok(());
+    }
+
+    refresh_unlocked(state).await
+}
+
+/// Fresh only while the snapshot itself is still there, Redis may evict it before the marker.
+async fn prices_fresh(redis: &mut ConnectionManager) -> Result<bool> {
+    let existing: usize = redis
+        .exists(&[PRICES_FRESH_KEY, PRICES_KEY])
+        .await
+        .map_err(|_| Error::RedisGetFail)?;
+
+    Ok(existing == 2)
+}
+
+async fn refresh_unlocked(state: &mut AppState) -> Result<()> {
+    let token = lock_token();
+
+    let acquired: Option<String> = redis::cmd("SET")
+        .arg(PRICES_LOCK_KEY)
+        .arg(&token)
+        .arg("NX")
+        .arg("EX")
+        .arg(LOCK_SECS)
+        .query_async(&mut state.redis)
+        .await
+        .map_err(|_| Error::RedisSetFail)?;
+
+    if acquired.is_none() {
+        return wait_for_prices(&mut state.redis).await;
+    }
+
+    let redis = state.redis.clone();
+
+    let result = tokio::select! {
+        result = store_prices(state) => result,
+        lost = renew_lock(redis, &token) => lost,
+    };
+
+    Script::new(
+        r#"
+        if redis.call("get", KEYS[1]) == ARGV[1] then
+            return redis.call("del", KEYS[1])
+        end
+        return 0
+        "#,
+    )
+    .key(PRICES_LOCK_KEY)
+    .arg(&token)
+    .invoke_async::<_, i32>(&mut state.redis)
+    .await
+    .map_err(|_| Error::RedisSetFail)?;
+
+    result
+}
+
+/// Keeps extending the lock while it is held with `token`.
+///
+/// Only returns once the lock was taken over by someone else, which aborts the refresh.
+async fn renew_lock(mut redis: ConnectionManager, token: &str) -> Result<()> {
+    let script = Script::new(
+        r#"
+        if redis.call("get", KEYS[1]) == ARGV[1] then
+            return redis.call("expire", KEYS[1], ARGV[2])
+        end
+        return 0
+        "#,
+    );
+
+    loop {
+        tokio::time::sleep(LOCK_RENEW_INTERVAL).await;
+
+        let renewed = script
+            .key(PRICES_LOCK_KEY)
+            .arg(token)
+            .arg(LOCK_SECS)
+            .invoke_async::<_, i32>(&mut redis)
+            .await;
+
+        match renewed {
+            Ok(0) => return Err(Error::RedisSetFail),
+            Err(e) => println!("ERROR - price lock renewal {e:?}"),
+            _ => {}
+        }
+    }
+}
+
+/// Waits for another instance holding the lock to finish its refresh.
+async fn wait_for_prices(redis: &mut ConnectionManager) -> Result<()> {
+    for _ in 0..LOCK_SECS * 2 {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        let locked: bool = redis
+            .exists(PRICES_LOCK_KEY)
+            .await
+            .map_err(|_| Error::RedisGetFail)?;
+
+        if !locked {
+            break;
+        }
+    }
+
+    let exists: bool = redis
+        .exists(PRICES_KEY)
+        .await
+        .map_err(|_| Error::RedisGetFail)?;
+
+    if !exists {
+        return Err(Error::PricesFetchFail);
+    }
+
+    Ok(())
+}
+
+async fn store_prices(state: &mut AppState) -> Result<()> {
+    let new_prices = state.prices.fetch_prices().await?;
+
+    let names: Vec<String> = match new_prices.as_object() {
+        Some(prices_object) if !prices_object.is_empty() => prices_object.keys().cloned().collect(),
+        _ => return Err(Error::PricesParseFail),
+    };
+
+    state
+        .redis
+        .json_set::<_, _, _, ()>(PRICES_KEY, ".", &new_prices)
+        .await
+        .map_err(|_| Error::RedisSetFail)?;
+
+    state
+        .redis
+        .expire::<_, ()>(PRICES_KEY, STALE_SECS)
+        .await
+        .map_err(|_| Error::RedisExpireFail)?;
+
+    state
+        .redis
+        .set_ex::<_, _, ()>(PRICES_VERSION_KEY, lock_token(), STALE_SECS)
+        .await
+        .map_err(|_| Error::RedisSetFail)?;
+
+    sync_items(&state.pg, names).await?;
+
+    // recorded once per stored snapshot, a failed insert only leaves a gap in the history
+    if let Err(e) = insert_price_history(&state.pg, &new_prices).await {
+        println!("ERROR - price history {e:?}");
+    }
+
+    // marked fresh last, so a failure above is retried by the next caller
+    state
+        .redis
+        .set_ex::<_, _, ()>(PRICES_FRESH_KEY, 1, FRESH_SECS)
+        .await
+        .map_err(|_| Error::RedisSetFail)
+}
+
+fn lock_token() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+
+    format!("{}-{nanos}", std::process::id())
+}
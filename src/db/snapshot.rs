@@ -0,0 +1,121 @@
+use std::{collections::HashMap, sync::Arc};
+
+use arc_swap::ArcSwapOption;
+use redis::{AsyncCommands, JsonAsyncCommands};
+use serde_json::Value;
+use tokio::sync::Mutex;
+
+use crate::{
+    api::Prices,
+    db::refresh::{ensure_prices, PRICES_KEY, PRICES_VERSION_KEY},
+    error::{Error, Result},
+    state::AppState,
+};
+
+/// Parsed copy of the Redis price snapshot, tagged with the version it was read at.
+pub struct LocalPrices {
+    version: Option<String>,
+    /// `None` marks an entry whose prices failed to parse.
+    items: HashMap<String, Option<Prices>>,
+}
+
+impl LocalPrices {
+    pub fn get(&self, market_hash_name: &str) -> Option<&Option<Prices>> {
+        self.items.get(market_hash_name)
+    }
+
+    pub fn contains(&self, market_hash_name: &str) -> bool {
+        self.items.contains_key(market_hash_name)
+    }
+
+    /// Parses the snapshot JSON, keeping entries whose prices fail to parse as `None`.
+    fn parse(version: Option<String>, prices: &str) -> Result<Self> {
+        let prices: HashMap<String, Value> =
+            serde_json::from_str(prices).map_err(|_| Error::PricesParseFail)?;
+
+        let items = prices
+            .into_iter()
+            .map(|(name, value)| (name, serde_json::from_value(value).ok()))
+            .collect();
+
+        Ok(Self { version, items })
+    }
+}
+
+/// In-process price snapshot, swapped whole whenever Redis holds a newer version.
+#[derive(Default)]
+pub struct PriceCache {
+    current: ArcSwapOption<LocalPrices>,
+    reload: Mutex<()>,
+}
+
+/// Returns the local snapshot, reloading it from Redis if another refresh
+/// has bumped the version since it was read.
+pub async fn local_prices(state: &mut AppState) -> Result<Arc<LocalPrices>> {
+    ensure_prices(state).await?;
+
+    let version = prices_version(state).await?;
+
+    if let Some(current) = current_at(state, &version) {
+        return Ok(current);
+    }
+
+    let cache = state.price_cache.clone();
+    let _guard = cache.reload.lock().await;
+
+    if let Some(current) = current_at(state, &version) {
+        return Ok(current);
+    }
+
+    let prices: String = state
+        .redis
+        .json_get(PRICES_KEY, ".")
+        .await
+        .map_err(|_| Error::RedisGetFail)?;
+
+    let snapshot = Arc::new(LocalPrices::parse(version, &prices)?);
+
+    cache.current.store(Some(snapshot.clone()));
+
+    Ok(snapshot)
+}
+
+fn current_at(state: &AppState, version: &Option<String>) -> Option<Arc<LocalPrices>> {
+    state
+        .price_cache
+        .current
+        .load_full()
+        .filter(|current| &current.version == version)
+}
+
+async fn prices_version(state: &mut AppState) -> Result<Option<String>> {
+    state
+        .redis
+        .get(PRICES_VERSION_KEY)
+        .await
+        .map_err(|_| Error::RedisGetFail)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::steam::market_hash_name::NAME_CORPUS;
+
+    #[test]
+    fn corpus_names_are_found() {
+        let prices: serde_json::Map<String, Value> = NAME_CORPUS
+            .iter()
+            .map(|name| (name.to_string(), json!({ "steam": null })))
+            .collect();
+
+        let snapshot = LocalPrices::parse(None, &Value::Object(prices).to_string()).unwrap();
+
+        for name in NAME_CORPUS {
+            assert!(matches!(snapshot.get(name), Some(Some(_))), "{name}");
+        }
+
+        assert!(!snapshot.contains("StatTrak M4A4 | Howl (Minimal Wear)"));
+    }
+}
@@ -0,0 +1,232 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{types::Decimal, FromRow, PgPool, Postgres, Type};
+use time::OffsetDateTime;
+
+use crate::{
+    db::{investment::Currencies, pg_error},
+    error::{Error, Result},
+};
+
+/// Largest price a `numeric(10, 2)` column holds, in cents.
+const MAX_PRICE_CENTS: i64 = 9_999_999_999;
+
+#[derive(Debug, Type, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[sqlx(type_name = "transaction_kind", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum TransactionKind {
+    Buy,
+    Sell,
+}
+
+#[derive(Debug, FromRow, Serialize, Clone)]
+pub struct Transaction {
+    pub tx_id: i32,
+    pub inv_id: i32,
+    pub steam_id: String,
+    pub kind: TransactionKind,
+    pub amount: i32,
+    pub price: Decimal,
+    pub currency: Currencies,
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+pub async fn get_transactions(
+    pool: &PgPool,
+    steam_id: String,
+    inv_id: i32,
+) -> Result<Vec<Transaction>> {
+    let sql = r"
+        select * from transactions
+        where steam_id = $1 and inv_id = $2
+        order by created_at asc, tx_id asc
+    ";
+
+    let txs: Vec<Transaction> = sqlx::query_as(sql)
+        .bind(steam_id)
+        .bind(inv_id)
+        .fetch_all(pool)
+        .await
+        .map_err(|_| Error::PgFetchFail)?;
+
+    let txs = txs
+        .into_iter()
+        .map(|mut t| {
+            t.price.rescale(2);
+            t
+        })
+        .collect::<Vec<Transaction>>();
+
+    Ok(txs)
+}
+
+/// Rounds a price to cents, rejecting negative, non-finite and too large ones.
+pub fn valid_price(price: f32) -> Result<Decimal> {
+    Decimal::try_from(price)
+        .ok()
+        .map(|price| price.round_dp(2))
+        .filter(|price| !price.is_sign_negative() && *price <= Decimal::new(MAX_PRICE_CENTS, 2))
+        .ok_or(Error::TransactionInvalidPrice)
+}
+
+/// Adds a transaction and brings the investment's position in line with the ledger.
+///
+/// Buys must be in the investment's currency so they can be averaged into its cost.
+pub async fn create_transaction(
+    pool: &PgPool,
+    steam_id: String,
+    inv_id: i32,
+    kind: TransactionKind,
+    amount: i32,
+    price: f32,
+    currency: Currencies,
+) -> Result<Transaction> {
+    if amount <= 0 {
+        return Err(Error::TransactionInvalidAmount);
+    }
+
+    let price = valid_price(price)?;
+
+    let mut tx = pool.begin().await.map_err(|_| Error::PgInsertFail)?;
+
+    let inv_currency = lock_investment(&mut tx, &steam_id, inv_id).await?;
+
+    if kind == TransactionKind::Buy && currency != inv_currency {
+        return Err(Error::TransactionCurrencyMismatch);
+    }
+
+    let sql = r"
+        insert into transactions
+        (inv_id, steam_id, kind, amount, price, currency) values ($1, $2, $3, $4, $5, $6)
+        returning *
+    ";
+
+    let mut transaction: Transaction = sqlx::query_as(sql)
+        .bind(inv_id)
+        .bind(steam_id)
+        .bind(kind)
+        .bind(amount)
+        .bind(price)
+        .bind(currency)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| {
+            println!("{e}");
+            Error::PgInsertFail
+        })?;
+
+    sync_position(&mut tx, inv_id).await?;
+
+    tx.commit().await.map_err(|_| Error::PgInsertFail)?;
+
+    transaction.price.rescale(2);
+
+    Ok(transaction)
+}
+
+/// Removes a transaction and brings the investment's position in line with the ledger.
+pub async fn drop_transaction(
+    pool: &PgPool,
+    steam_id: String,
+    inv_id: i32,
+    tx_id: i32,
+) -> Result<()> {
+    let mut tx = pool.begin().await.map_err(|_| Error::PgDeleteFail)?;
+
+    lock_investment(&mut tx, &steam_id, inv_id).await?;
+
+    let sql = r"
+        delete from transactions
+        where steam_id = $1 and inv_id = $2 and tx_id = $3
+    ";
+
+    let result = sqlx::query(sql)
+        .bind(&steam_id)
+        .bind(inv_id)
+        .bind(tx_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| Error::PgDeleteFail)?;
+
+    if result.rows_affected() == 0 {
+        return Err(Error::PgRowNotFound);
+    }
+
+    sync_position(&mut tx, inv_id).await?;
+
+    tx.commit().await.map_err(|_| Error::PgDeleteFail)
+}
+
+/// Locks the investment row for the rest of the transaction and returns its currency.
+async fn lock_investment(
+    tx: &mut sqlx::Transaction<'_, Postgres>,
+    steam_id: &str,
+    inv_id: i32,
+) -> Result<Currencies> {
+    let sql = r"
+        select currency from investments
+        where steam_id = $1 and inv_id = $2
+        for update
+    ";
+
+    let (currency,): (Currencies,) = sqlx::query_as(sql)
+        .bind(steam_id)
+        .bind(inv_id)
+        .fetch_one(&mut **tx)
+        .await
+        .map_err(pg_error(Error::PgFetchFail))?;
+
+    Ok(currency)
+}
+
+/// Sets the investment's amount to the open position and its cost to the average
+/// buy price, failing if sells exceed buys.
+pub async fn sync_position(tx: &mut sqlx::Transaction<'_, Postgres>, inv_id: i32) -> Result<()> {
+    let sql = r"
+        update investments inv
+        set amount = t.open_amount, cost = coalesce(t.avg_cost, inv.cost)
+        from (
+            select
+                coalesce(sum(case when kind = 'buy' then amount else -amount end), 0) as open_amount,
+                sum(price * amount) filter (where kind = 'buy')
+                    / nullif(sum(amount) filter (where kind = 'buy'), 0) as avg_cost
+            from transactions
+            where inv_id = $1
+        ) t
+        where inv.inv_id = $1
+        returning inv.amount
+    ";
+
+    let (amount,): (i32,) = sqlx::query_as(sql)
+        .bind(inv_id)
+        .fetch_one(&mut **tx)
+        .await
+        .map_err(pg_error(Error::PgUpdateFail))?;
+
+    if amount < 0 {
+        return Err(Error::TransactionInvalidAmount);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_price_rounds_to_cents() {
+        assert_eq!(valid_price(0.0).unwrap(), Decimal::ZERO);
+        assert_eq!(valid_price(19.999).unwrap(), Decimal::new(2000, 2));
+    }
+
+    #[test]
+    fn valid_price_rejects_what_the_ledger_cannot_hold() {
+        for price in [-0.5, f32::NAN, f32::INFINITY, 1e9] {
+            assert!(
+                matches!(valid_price(price), Err(Error::TransactionInvalidPrice)),
+                "{price}"
+            );
+        }
+    }
+}
@@ -22,7 +22,8 @@ pub enum Error {
     ItemsFetchFail,
     ItemsParseFail,
     ItemMissingImage,
-    InvalidHashName,
+    InvalidHashName(String),
+    ItemNotFound(String),
 
     SteamMissingId,
     SteamMissingAsset,
@@ -36,18 +37,78 @@ pub enum Error {
     PgInsertFail,
     PgDeleteFail,
     PgUpdateFail,
+    PgRowNotFound,
+
+    InvestmentUnknownCollection,
+    InvestmentHasTransactions,
+
+    TransactionInvalidAmount,
+    TransactionInvalidPrice,
+    TransactionCurrencyMismatch,
 
     JwtInvalidToken,
+    JwtExpired,
+    JwtWrongApp,
+    JwtKeyLoadFail,
+    JwtKeyConfig(&'static str),
 
     AuthMissingCookie,
+    AuthMalformedHeader,
+    AuthInvalidApiKey,
+    AuthReadOnlyKey,
+    AuthKeyNotAllowed,
+    AuthNotAdmin,
+
+    RateLimited,
 }
 #[derive(Serialize)]
 #[allow(non_camel_case_types)]
 pub enum ClientError {
     NO_AUTH,
+    MALFORMED_AUTH,
+    TOKEN_EXPIRED,
+    READ_ONLY_KEY,
+    KEY_NOT_ALLOWED,
+    NOT_ADMIN,
+    RATE_LIMITED,
+    INVALID_ITEM,
+    INVALID_AMOUNT,
+    INVALID_PRICE,
+    CURRENCY_MISMATCH,
+    HAS_TRANSACTIONS,
+    NOT_FOUND,
+    UNKNOWN_COLLECTION,
+    UPSTREAM_ERROR,
+    UPSTREAM_UNAVAILABLE,
     SERVICE_ERROR,
 }
 
+impl ClientError {
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::NO_AUTH => "Authentication required",
+            Self::MALFORMED_AUTH => "Authorization header must be `Bearer <token>`",
+            Self::TOKEN_EXPIRED => "Access token has expired",
+            Self::READ_ONLY_KEY => "API key is read-only",
+            Self::KEY_NOT_ALLOWED => "API keys cannot be managed with an API key",
+            Self::NOT_ADMIN => "Admin access required",
+            Self::RATE_LIMITED => "Too many requests, try again in a minute",
+            Self::INVALID_ITEM => "Unknown item market hash name",
+            Self::INVALID_AMOUNT => "Amount exceeds the open position or is not positive",
+            Self::INVALID_PRICE => "Price must be a non-negative number below 100000000",
+            Self::CURRENCY_MISMATCH => "Buys must be in the investment's currency",
+            Self::HAS_TRANSACTIONS => {
+                "Amount and cost follow the investment's transactions, edit those instead"
+            }
+            Self::NOT_FOUND => "Resource not found",
+            Self::UNKNOWN_COLLECTION => "Collection does not exist",
+            Self::UPSTREAM_ERROR => "Upstream data source returned an invalid response",
+            Self::UPSTREAM_UNAVAILABLE => "Upstream data source is unavailable",
+            Self::SERVICE_ERROR => "Internal service error",
+        }
+    }
+}
+
 impl IntoResponse for Error {
     fn into_response(self) -> Response {
         let mut response = StatusCode::INTERNAL_SERVER_ERROR.into_response();
@@ -61,16 +122,79 @@ impl IntoResponse for Error {
 impl Error {
     pub fn client_status_and_error(&self) -> (StatusCode, ClientError) {
         match self {
-            Self::JwtInvalidToken => (StatusCode::UNAUTHORIZED, ClientError::NO_AUTH),
+            Self::JwtInvalidToken | Self::JwtWrongApp | Self::AuthInvalidApiKey => {
+                (StatusCode::UNAUTHORIZED, ClientError::NO_AUTH)
+            }
+
+            Self::JwtExpired => (StatusCode::UNAUTHORIZED, ClientError::TOKEN_EXPIRED),
+
+            Self::AuthMalformedHeader => (StatusCode::BAD_REQUEST, ClientError::MALFORMED_AUTH),
+
+            Self::AuthReadOnlyKey => (StatusCode::FORBIDDEN, ClientError::READ_ONLY_KEY),
+
+            Self::AuthKeyNotAllowed => (StatusCode::FORBIDDEN, ClientError::KEY_NOT_ALLOWED),
 
             Self::AuthMissingCookie => (StatusCode::FORBIDDEN, ClientError::NO_AUTH),
 
+            Self::AuthNotAdmin => (StatusCode::FORBIDDEN, ClientError::NOT_ADMIN),
+
+            Self::RateLimited => (StatusCode::TOO_MANY_REQUESTS, ClientError::RATE_LIMITED),
+
+            Self::InvalidHashName(_) => (StatusCode::BAD_REQUEST, ClientError::INVALID_ITEM),
+
+            Self::PgRowNotFound | Self::ItemMissingImage | Self::ItemNotFound(_) => {
+                (StatusCode::NOT_FOUND, ClientError::NOT_FOUND)
+            }
+
+            Self::InvestmentUnknownCollection => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                ClientError::UNKNOWN_COLLECTION,
+            ),
+
+            Self::PricesFetchFail
+            | Self::RatesFetchFail
+            | Self::ItemsFetchFail
+            | Self::InventoryFetchFail
+            | Self::JwtKeyLoadFail => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                ClientError::UPSTREAM_UNAVAILABLE,
+            ),
+
+            Self::PricesParseFail
+            | Self::RatesParseFail
+            | Self::ItemsParseFail
+            | Self::InventoryParseFail => (StatusCode::BAD_GATEWAY, ClientError::UPSTREAM_ERROR),
+
+            Self::TransactionInvalidAmount => {
+                (StatusCode::BAD_REQUEST, ClientError::INVALID_AMOUNT)
+            }
+
+            Self::TransactionInvalidPrice => (StatusCode::BAD_REQUEST, ClientError::INVALID_PRICE),
+
+            Self::TransactionCurrencyMismatch => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                ClientError::CURRENCY_MISMATCH,
+            ),
+
+            Self::InvestmentHasTransactions => {
+                (StatusCode::CONFLICT, ClientError::HAS_TRANSACTIONS)
+            }
+
             _ => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 ClientError::SERVICE_ERROR,
             ),
         }
     }
+
+    pub fn client_details(&self) -> Option<String> {
+        match self {
+            Self::InvalidHashName(market_hash_name) | Self::ItemNotFound(market_hash_name) => {
+                Some(market_hash_name.clone())
+            }
+            _ => None,
+        }
+    }
 }
 
 impl std::fmt::Display for Error {
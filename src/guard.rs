@@ -1,19 +1,160 @@
-use axum::{middleware::Next, response::Response};
-use http::Request;
+use axum::{extract::State, middleware::Next, response::Response};
+use http::{header::AUTHORIZATION, HeaderMap, Method, Request};
+use sqlx::PgPool;
 use tower_cookies::Cookies;
 
 use crate::{
+    db::api_key::{authenticate_api_key, ApiKeyAuth, ApiKeyScope, API_KEY_PREFIX},
     error::{Error, Result},
-    jwt::verify_token,
+    jwt::{Account, User},
+    state::AppState,
 };
 
-pub async fn guard<T>(cookies: Cookies, mut req: Request<T>, next: Next<T>) -> Result<Response> {
-    let token = &cookies.get("access").ok_or(Error::AuthMissingCookie)?;
-    let token = token.value();
+/// Authenticates with the `Authorization: Bearer` header if the request has one,
+/// otherwise with the `access` cookie. A present but malformed header is rejected
+/// rather than falling back to the cookie.
+///
+/// Bearer values starting with `cst_` are personal API keys, read-only keys are
+/// limited to `GET` requests.
+pub async fn guard<T>(
+    State(state): State<AppState>,
+    cookies: Cookies,
+    mut req: Request<T>,
+    next: Next<T>,
+) -> Result<Response> {
+    let token = match bearer_token(req.headers())? {
+        Some(token) => token,
+        _ => cookies
+            .get("access")
+            .ok_or(Error::AuthMissingCookie)?
+            .value()
+            .to_string(),
+    };
 
-    let user = verify_token(token)?;
+    if token.starts_with(API_KEY_PREFIX) {
+        let auth = api_key_auth(&state.pg, &token, req.method()).await?;
+
+        req.extensions_mut().insert(User {
+            user_id: auth.user_id,
+            steam: Account {
+                id: Some(auth.steam_id.clone()),
+            },
+        });
+        req.extensions_mut().insert(auth);
+
+        return Ok(next.run(req).await);
+    }
+
+    let user = state.jwt.verify_token(&token).await?;
 
     req.extensions_mut().insert(user);
 
     Ok(next.run(req).await)
 }
+
+/// Authenticates an API key and checks its scope allows `method`.
+async fn api_key_auth(pool: &PgPool, key: &str, method: &Method) -> Result<ApiKeyAuth> {
+    let auth = authenticate_api_key(pool, key).await?;
+
+    if auth.scope == ApiKeyScope::Read && method != Method::GET {
+        return Err(Error::AuthReadOnlyKey);
+    }
+
+    Ok(auth)
+}
+
+fn bearer_token(headers: &HeaderMap) -> Result<Option<String>> {
+    let Some(header) = headers.get(AUTHORIZATION) else {
+        return Ok(None);
+    };
+
+    let header = header.to_str().map_err(|_| Error::AuthMalformedHeader)?;
+
+    match header.split_once(' ') {
+        Some((scheme, token))
+            if scheme.eq_ignore_ascii_case("bearer")
+                && !token.is_empty()
+                && !token.contains(' ') =>
+        {
+            Ok(Some(token.to_string()))
+        }
+        _ => Err(Error::AuthMalformedHeader),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::HeaderValue;
+
+    use super::*;
+    use crate::db::{api_key::create_api_key, user::create_user};
+
+    const OWNER: &str = "76561198000000001";
+
+    fn authorization(value: &[u8]) -> Result<Option<String>> {
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_bytes(value).unwrap());
+
+        bearer_token(&headers)
+    }
+
+    #[test]
+    fn missing_header_falls_back_to_cookie() {
+        assert!(matches!(bearer_token(&HeaderMap::new()), Ok(None)));
+    }
+
+    #[test]
+    fn accepts_bearer_in_any_case() {
+        assert_eq!(authorization(b"Bearer x").unwrap().as_deref(), Some("x"));
+        assert_eq!(authorization(b"bearer x").unwrap().as_deref(), Some("x"));
+    }
+
+    #[test]
+    fn rejects_malformed_headers() {
+        for value in [
+            &b"Basic x"[..],
+            b"Bearer",
+            b"Bearer ",
+            b"Bearer x y",
+            b"Bearer \xff\xfe",
+        ] {
+            assert!(
+                matches!(authorization(value), Err(Error::AuthMalformedHeader)),
+                "{}",
+                String::from_utf8_lossy(value)
+            );
+        }
+    }
+
+    #[sqlx::test]
+    async fn read_key_is_limited_to_get(pool: PgPool) {
+        create_user(&pool, OWNER).await.unwrap();
+
+        let (_, key) = create_api_key(&pool, OWNER, 1, "read", ApiKeyScope::Read)
+            .await
+            .unwrap();
+
+        assert!(api_key_auth(&pool, &key, &Method::GET).await.is_ok());
+
+        for method in [Method::POST, Method::PUT, Method::PATCH, Method::DELETE] {
+            assert!(
+                matches!(
+                    api_key_auth(&pool, &key, &method).await,
+                    Err(Error::AuthReadOnlyKey)
+                ),
+                "{method}"
+            );
+        }
+    }
+
+    #[sqlx::test]
+    async fn read_write_key_allows_any_method(pool: PgPool) {
+        create_user(&pool, OWNER).await.unwrap();
+
+        let (_, key) = create_api_key(&pool, OWNER, 1, "write", ApiKeyScope::ReadWrite)
+            .await
+            .unwrap();
+
+        assert!(api_key_auth(&pool, &key, &Method::DELETE).await.is_ok());
+    }
+}
@@ -0,0 +1,164 @@
+use std::{env, sync::RwLock, time::Duration};
+
+use serde::Serialize;
+use time::OffsetDateTime;
+
+use crate::{
+    db::{
+        catalog::{catalog_is_empty, import_catalog},
+        rate::refresh_currency_rates,
+        refresh::{refresh_prices, FRESH_SECS},
+    },
+    error::Result,
+    state::AppState,
+};
+
+#[derive(Clone, Copy)]
+enum Job {
+    Prices,
+    Rates,
+    Catalog,
+}
+
+const JOBS: [Job; 3] = [Job::Prices, Job::Rates, Job::Catalog];
+
+impl Job {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Prices => "prices",
+            Self::Rates => "currency_rates",
+            Self::Catalog => "item_catalog",
+        }
+    }
+
+    /// Interval from `JOB_<NAME>_INTERVAL` in seconds, `0` disables the job.
+    ///
+    /// Prices are only refreshed once stale, so their interval is capped at half
+    /// of [`FRESH_SECS`] to replace a stale snapshot soon after it expires.
+    fn interval(&self) -> u64 {
+        let (var, default) = match self {
+            Self::Prices => ("JOB_PRICES_INTERVAL", 3600 * 4),
+            Self::Rates => ("JOB_RATES_INTERVAL", 3600),
+            Self::Catalog => ("JOB_CATALOG_INTERVAL", 3600 * 12),
+        };
+
+        let interval = env::var(var)
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(default);
+
+        match self {
+            Self::Prices => interval.min(FRESH_SECS as u64 / 2),
+            _ => interval,
+        }
+    }
+
+    /// Whether the job has to run even though it is disabled, which is the case
+    /// for an empty catalog since icons and suggestions are served from it.
+    async fn required(&self, state: &AppState) -> bool {
+        match self {
+            Self::Catalog => catalog_is_empty(&state.pg).await.unwrap_or(true),
+            _ => false,
+        }
+    }
+
+    /// Runs the job, recording the outcome in its status.
+    async fn run_tracked(&self, state: &mut AppState) {
+        state.jobs.update(self.name(), |s| s.running = true);
+
+        let result = self.run(state).await;
+        let now = OffsetDateTime::now_utc();
+
+        state.jobs.update(self.name(), |s| {
+            s.running = false;
+
+            match result {
+                Ok(_) => s.last_success = Some(now),
+                Err(e) => {
+                    println!("ERROR - job {} {e:?}", self.name());
+
+                    s.last_failure = Some(now);
+                    s.last_error = Some(e.to_string());
+                }
+            }
+        });
+    }
+
+    async fn run(&self, state: &mut AppState) -> Result<()> {
+        match self {
+            Self::Prices => refresh_prices(state).await,
+            Self::Rates => refresh_currency_rates(&mut state.redis).await.map(|_| ()),
+            Self::Catalog => import_catalog(&state.pg).await,
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct JobStatus {
+    name: &'static str,
+    interval_secs: u64,
+    running: bool,
+    #[serde(with = "time::serde::rfc3339::option")]
+    last_success: Option<OffsetDateTime>,
+    #[serde(with = "time::serde::rfc3339::option")]
+    last_failure: Option<OffsetDateTime>,
+    last_error: Option<String>,
+}
+
+/// Last outcome of every background refresh job.
+#[derive(Default)]
+pub struct Jobs {
+    statuses: RwLock<Vec<JobStatus>>,
+}
+
+impl Jobs {
+    pub fn statuses(&self) -> Vec<JobStatus> {
+        self.statuses.read().unwrap().clone()
+    }
+
+    fn update(&self, name: &str, f: impl FnOnce(&mut JobStatus)) {
+        let mut statuses = self.statuses.write().unwrap();
+
+        if let Some(status) = statuses.iter_mut().find(|s| s.name == name) {
+            f(status);
+        }
+    }
+}
+
+/// Spawns one refresh loop per enabled job, each running once right away.
+///
+/// A disabled job still runs once if the API cannot work without it, see [`Job::required`].
+pub fn start(state: AppState) {
+    for job in JOBS {
+        let interval = job.interval();
+
+        state.jobs.statuses.write().unwrap().push(JobStatus {
+            name: job.name(),
+            interval_secs: interval,
+            running: false,
+            last_success: None,
+            last_failure: None,
+            last_error: None,
+        });
+
+        let mut state = state.clone();
+
+        tokio::spawn(async move {
+            if interval == 0 {
+                if job.required(&state).await {
+                    job.run_tracked(&mut state).await;
+                }
+
+                return;
+            }
+
+            let mut ticker = tokio::time::interval(Duration::from_secs(interval));
+
+            loop {
+                ticker.tick().await;
+
+                job.run_tracked(&mut state).await;
+            }
+        });
+    }
+}
@@ -1,18 +1,190 @@
-use std::env;
+use std::{
+    collections::HashMap,
+    env,
+    sync::{Mutex, RwLock},
+    time::{Duration, Instant},
+};
 
-use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use jsonwebtoken::{
+    decode, decode_header, errors::ErrorKind, jwk::JwkSet, Algorithm, DecodingKey, Validation,
+};
 use serde::{Deserialize, Serialize};
 
 use crate::error::{Error, Result};
 
-pub fn verify_token(token: &str) -> Result<User> {
-    let decoding_key =
-        DecodingKey::from_rsa_pem(env::var("TOKEN_PUBLIC_KEY").unwrap().as_bytes()).unwrap();
+/// Shortest time between two JWKS downloads triggered by unknown `kid`s.
+const JWKS_REFETCH_INTERVAL: Duration = Duration::from_secs(60);
+/// How long keys from the JWKS URL are used before it is fetched again.
+const JWKS_TTL: Duration = Duration::from_secs(600);
 
-    let token_data = decode::<Claims>(token, &decoding_key, &Validation::new(Algorithm::RS256))
-        .map_err(|_| Error::JwtInvalidToken)?;
+/// Public keys tokens are verified with.
+///
+/// Keys come from `TOKEN_PUBLIC_KEY` (PEM), `TOKEN_JWKS_FILE` and `TOKEN_JWKS_URL`.
+/// The PEM key verifies tokens without a `kid` and ones whose `kid` no set knows.
+/// The URL is fetched again after `JWKS_TTL` or when a token has an unknown `kid`,
+/// so rotated keys are picked up and removed ones stop being accepted within the TTL.
+pub struct JwtKeys {
+    default: Option<DecodingKey>,
+    file_keys: HashMap<String, DecodingKey>,
+    /// Replaced whole on every fetch of `jwks_url`.
+    url_keys: RwLock<HashMap<String, DecodingKey>>,
+    jwks_url: Option<String>,
+    last_fetch: Mutex<Option<Instant>>,
+    /// Accepted `app_id` claims from `TOKEN_APP_IDS`, any if unset.
+    app_ids: Option<Vec<String>>,
+}
+
+impl JwtKeys {
+    /// Fails with [`Error::JwtKeyConfig`] naming the variable that is malformed,
+    /// or all of them if none is set.
+    pub async fn from_env() -> Result<Self> {
+        let default = match env::var("TOKEN_PUBLIC_KEY") {
+            Ok(pem) => Some(
+                DecodingKey::from_rsa_pem(pem.as_bytes())
+                    .map_err(|_| Error::JwtKeyConfig("TOKEN_PUBLIC_KEY"))?,
+            ),
+            _ => None,
+        };
+
+        let file_keys = match env::var("TOKEN_JWKS_FILE") {
+            Ok(path) => {
+                let data = tokio::fs::read(path)
+                    .await
+                    .map_err(|_| Error::JwtKeyConfig("TOKEN_JWKS_FILE"))?;
+
+                let set: JwkSet = serde_json::from_slice(&data)
+                    .map_err(|_| Error::JwtKeyConfig("TOKEN_JWKS_FILE"))?;
+
+                jwk_keys(&set)
+            }
+            _ => HashMap::new(),
+        };
+
+        let jwks_url = env::var("TOKEN_JWKS_URL").ok();
+
+        // an unreachable URL is retried on the first token rather than failing startup
+        let url_keys = match &jwks_url {
+            Some(url) => match fetch_jwks(url).await {
+                Ok(keys) => Some(keys),
+                Err(e) => {
+                    println!("ERROR - jwks fetch {e:?}");
+
+                    None
+                }
+            },
+            _ => None,
+        };
+
+        if default.is_none() && file_keys.is_empty() && jwks_url.is_none() {
+            return Err(Error::JwtKeyConfig(
+                "TOKEN_PUBLIC_KEY, TOKEN_JWKS_FILE or TOKEN_JWKS_URL",
+            ));
+        }
+
+        let app_ids = env::var("TOKEN_APP_IDS")
+            .ok()
+            .map(|ids| ids.split(',').map(|id| id.trim().to_string()).collect());
+
+        Ok(Self {
+            default,
+            file_keys,
+            last_fetch: Mutex::new(url_keys.as_ref().map(|_| Instant::now())),
+            url_keys: RwLock::new(url_keys.unwrap_or_default()),
+            jwks_url,
+            app_ids,
+        })
+    }
+
+    pub async fn verify_token(&self, token: &str) -> Result<User> {
+        let header = decode_header(token).map_err(|_| Error::JwtInvalidToken)?;
+
+        let key = match &header.kid {
+            Some(kid) => self.key(kid).await,
+            _ => None,
+        };
+
+        let key = key
+            .or_else(|| self.default.clone())
+            .ok_or(Error::JwtInvalidToken)?;
+
+        let token_data = decode::<Claims>(token, &key, &Validation::new(Algorithm::RS256))
+            .map_err(|e| match e.kind() {
+                ErrorKind::ExpiredSignature => Error::JwtExpired,
+                _ => Error::JwtInvalidToken,
+            })?;
+
+        if let Some(app_ids) = &self.app_ids {
+            if !app_ids.contains(&token_data.claims.app_id) {
+                return Err(Error::JwtWrongApp);
+            }
+        }
+
+        Ok(token_data.claims.user)
+    }
+
+    async fn key(&self, kid: &str) -> Option<DecodingKey> {
+        if let Some(key) = self.file_keys.get(kid) {
+            return Some(key.clone());
+        }
+
+        if let Some(url) = &self.jwks_url {
+            self.refresh_url_keys(url, kid).await;
+        }
+
+        self.url_keys.read().unwrap().get(kid).cloned()
+    }
+
+    /// Fetches the URL again once its keys are older than `JWKS_TTL`, or for a `kid`
+    /// they miss if the last fetch is older than `JWKS_REFETCH_INTERVAL`.
+    ///
+    /// The old keys are kept if the fetch fails.
+    async fn refresh_url_keys(&self, url: &str, kid: &str) {
+        {
+            let mut last_fetch = self.last_fetch.lock().unwrap();
+
+            let due = match *last_fetch {
+                Some(at) if at.elapsed() < JWKS_REFETCH_INTERVAL => false,
+                Some(at) if at.elapsed() < JWKS_TTL => {
+                    !self.url_keys.read().unwrap().contains_key(kid)
+                }
+                _ => true,
+            };
+
+            if !due {
+                return;
+            }
+
+            *last_fetch = Some(Instant::now());
+        }
+
+        match fetch_jwks(url).await {
+            Ok(keys) => *self.url_keys.write().unwrap() = keys,
+            Err(e) => println!("ERROR - jwks refetch {e:?}"),
+        }
+    }
+}
+
+fn jwk_keys(set: &JwkSet) -> HashMap<String, DecodingKey> {
+    set.keys
+        .iter()
+        .filter_map(|jwk| {
+            let kid = jwk.common.key_id.clone()?;
+            let key = DecodingKey::from_jwk(jwk).ok()?;
+
+            Some((kid, key))
+        })
+        .collect()
+}
 
-    Ok(token_data.claims.user)
+async fn fetch_jwks(url: &str) -> Result<HashMap<String, DecodingKey>> {
+    let set: JwkSet = reqwest::get(url)
+        .await
+        .map_err(|_| Error::JwtKeyLoadFail)?
+        .json()
+        .await
+        .map_err(|_| Error::JwtKeyLoadFail)?;
+
+    Ok(jwk_keys(&set))
 }
 
 #[derive(Serialize, Deserialize)]
@@ -38,3 +210,196 @@ impl User {
 pub struct Account {
     pub id: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use jsonwebtoken::{encode, EncodingKey, Header};
+
+    use super::*;
+
+    const PRIVATE_KEY: &[u8] = include_bytes!("fixtures/jwt_test_key.pem");
+    const PUBLIC_KEY: &[u8] = include_bytes!("fixtures/jwt_test_key.pub.pem");
+    const JWKS: &str = include_str!("fixtures/jwt_test_jwks.json");
+
+    fn keys() -> JwtKeys {
+        JwtKeys {
+            default: Some(DecodingKey::from_rsa_pem(PUBLIC_KEY).unwrap()),
+            file_keys: HashMap::new(),
+            url_keys: RwLock::default(),
+            jwks_url: None,
+            last_fetch: Mutex::new(None),
+            app_ids: None,
+        }
+    }
+
+    /// Token for user `1` expiring `exp_in` seconds from now, negative for the past.
+    fn token(exp_in: i64) -> String {
+        signed(exp_in, None, "cs-tracker")
+    }
+
+    fn signed(exp_in: i64, kid: Option<&str>, app_id: &str) -> String {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        let claims = Claims {
+            user: User {
+                user_id: 1,
+                steam: Account {
+                    id: Some("76561198000000001".to_string()),
+                },
+            },
+            app_id: app_id.to_string(),
+            exp: (now + exp_in) as usize,
+        };
+
+        let mut header = Header::new(Algorithm::RS256);
+        header.kid = kid.map(str::to_string);
+
+        encode(
+            &header,
+            &claims,
+            &EncodingKey::from_rsa_pem(PRIVATE_KEY).unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn verifies_valid_token() {
+        let user = keys().verify_token(&token(3600)).await.unwrap();
+
+        assert_eq!(user.user_id, 1);
+    }
+
+    #[tokio::test]
+    async fn expired_token_is_jwt_expired() {
+        let result = keys().verify_token(&token(-3600)).await;
+
+        assert!(matches!(result, Err(Error::JwtExpired)));
+    }
+
+    fn file_keys() -> HashMap<String, DecodingKey> {
+        jwk_keys(&serde_json::from_str(JWKS).unwrap())
+    }
+
+    /// Serves the fixture JWKS on a local port and returns its URL.
+    fn serve_jwks() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let app = axum::Router::new().route(
+            "/jwks",
+            axum::routing::get(|| async { ([("content-type", "application/json")], JWKS) }),
+        );
+
+        tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(app.into_make_service()),
+        );
+
+        format!("http://{addr}/jwks")
+    }
+
+    #[tokio::test]
+    async fn selects_file_key_by_kid() {
+        let keys = JwtKeys {
+            default: None,
+            file_keys: file_keys(),
+            ..keys()
+        };
+
+        let user = keys
+            .verify_token(&signed(3600, Some("test"), "cs-tracker"))
+            .await
+            .unwrap();
+
+        assert_eq!(user.user_id, 1);
+
+        let result = keys
+            .verify_token(&signed(3600, Some("other"), "cs-tracker"))
+            .await;
+
+        assert!(matches!(result, Err(Error::JwtInvalidToken)));
+    }
+
+    #[tokio::test]
+    async fn unknown_kid_falls_back_to_default() {
+        let user = keys()
+            .verify_token(&signed(3600, Some("other"), "cs-tracker"))
+            .await
+            .unwrap();
+
+        assert_eq!(user.user_id, 1);
+    }
+
+    #[tokio::test]
+    async fn refetches_url_keys_after_ttl() {
+        let mut old = HashMap::new();
+        old.insert(
+            "old".to_string(),
+            DecodingKey::from_rsa_pem(PUBLIC_KEY).unwrap(),
+        );
+
+        let keys = JwtKeys {
+            default: None,
+            url_keys: RwLock::new(old),
+            jwks_url: Some(serve_jwks()),
+            last_fetch: Mutex::new(Instant::now().checked_sub(JWKS_TTL + Duration::from_secs(1))),
+            ..keys()
+        };
+
+        let user = keys
+            .verify_token(&signed(3600, Some("test"), "cs-tracker"))
+            .await
+            .unwrap();
+
+        assert_eq!(user.user_id, 1);
+
+        let result = keys
+            .verify_token(&signed(3600, Some("old"), "cs-tracker"))
+            .await;
+
+        assert!(matches!(result, Err(Error::JwtInvalidToken)));
+    }
+
+    #[tokio::test]
+    async fn rejects_token_for_other_app() {
+        let keys = JwtKeys {
+            app_ids: Some(vec!["cs-tracker".to_string(), "cs-tracker-web".to_string()]),
+            ..keys()
+        };
+
+        assert!(keys.verify_token(&token(3600)).await.is_ok());
+
+        let result = keys.verify_token(&signed(3600, None, "other-app")).await;
+
+        assert!(matches!(result, Err(Error::JwtWrongApp)));
+    }
+
+    #[tokio::test]
+    async fn from_env_starts_with_unreachable_jwks_url() {
+        env::remove_var("TOKEN_PUBLIC_KEY");
+        env::set_var(
+            "TOKEN_JWKS_FILE",
+            concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/src/fixtures/jwt_test_jwks.json"
+            ),
+        );
+        env::set_var("TOKEN_JWKS_URL", "http://127.0.0.1:1/jwks");
+        env::set_var("TOKEN_APP_IDS", "cs-tracker, cs-tracker-web");
+
+        let keys = JwtKeys::from_env().await.unwrap();
+
+        let user = keys
+            .verify_token(&signed(3600, Some("test"), "cs-tracker-web"))
+            .await
+            .unwrap();
+
+        assert_eq!(user.user_id, 1);
+    }
+}
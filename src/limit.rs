@@ -0,0 +1,137 @@
+use std::{
+    env,
+    net::SocketAddr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    extract::{ConnectInfo, State},
+    middleware::Next,
+    response::Response,
+};
+use http::{HeaderMap, Request};
+
+use crate::{
+    error::{Error, Result},
+    state::AppState,
+};
+
+const WINDOW_SECS: u64 = 60;
+
+/// Requests per minute from `PUBLIC_RATE_LIMIT`, `0` disables the limit.
+fn requests_per_window() -> u64 {
+    env::var("PUBLIC_RATE_LIMIT")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(120)
+}
+
+/// Number of proxies in front of the API from `TRUST_FORWARDED_FOR`, `1` if it is set
+/// to anything else, `None` if unset so `X-Forwarded-For` is ignored.
+fn trusted_hops() -> Option<usize> {
+    let hops = env::var("TRUST_FORWARDED_FOR").ok()?;
+
+    Some(hops.parse().ok().filter(|&hops| hops > 0).unwrap_or(1))
+}
+
+/// The `X-Forwarded-For` entry added by the outermost of `hops` trusted proxies,
+/// counted from the right since clients can put anything on the left.
+fn forwarded_ip(headers: &HeaderMap, hops: usize) -> Option<String> {
+    let entries: Vec<&str> = headers
+        .get_all("X-Forwarded-For")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .collect();
+
+    entries
+        .iter()
+        .rev()
+        .nth(hops - 1)
+        .filter(|ip| !ip.is_empty())
+        .map(|ip| ip.to_string())
+}
+
+/// The forwarded client address when proxies are trusted, the peer address otherwise.
+fn client_ip<T>(req: &Request<T>, addr: SocketAddr) -> String {
+    trusted_hops()
+        .and_then(|hops| forwarded_ip(req.headers(), hops))
+        .unwrap_or_else(|| addr.ip().to_string())
+}
+
+/// Fixed window rate limit per client IP, counted in Redis so all instances share it.
+///
+/// Requests are let through if Redis cannot be reached.
+pub async fn rate_limit<T>(
+    State(mut state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    req: Request<T>,
+    next: Next<T>,
+) -> Result<Response> {
+    let limit = requests_per_window();
+
+    if limit == 0 {
+        return Ok(next.run(req).await);
+    }
+
+    let window = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / WINDOW_SECS)
+        .unwrap_or_default();
+
+    let key = format!("rate_limit:{}:{window}", client_ip(&req, addr));
+
+    let count: redis::RedisResult<(u64,)> = redis::pipe()
+        .atomic()
+        .incr(&key, 1)
+        .expire(&key, WINDOW_SECS as usize)
+        .ignore()
+        .query_async(&mut state.redis)
+        .await;
+
+    match count {
+        Ok((count,)) if count > limit => Err(Error::RateLimited),
+        Err(e) => {
+            println!("ERROR - rate limit {e:?}");
+
+            Ok(next.run(req).await)
+        }
+        _ => Ok(next.run(req).await),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(values: &[&str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+
+        for value in values {
+            headers.append("X-Forwarded-For", value.parse().unwrap());
+        }
+
+        headers
+    }
+
+    #[test]
+    fn takes_right_most_entry_behind_one_proxy() {
+        let headers = headers(&["6.6.6.6, 203.0.113.7"]);
+
+        assert_eq!(forwarded_ip(&headers, 1).as_deref(), Some("203.0.113.7"));
+    }
+
+    #[test]
+    fn counts_trusted_hops_from_the_right() {
+        let headers = headers(&["6.6.6.6, 203.0.113.7", "10.0.0.2"]);
+
+        assert_eq!(forwarded_ip(&headers, 2).as_deref(), Some("203.0.113.7"));
+    }
+
+    #[test]
+    fn ignores_header_shorter_than_trusted_hops() {
+        assert_eq!(forwarded_ip(&headers(&["203.0.113.7"]), 2), None);
+        assert_eq!(forwarded_ip(&headers(&[]), 1), None);
+    }
+}
@@ -2,8 +2,12 @@ pub mod api;
 pub mod db;
 pub mod error;
 pub mod guard;
+pub mod jobs;
 pub mod jwt;
+pub mod limit;
+pub mod provider;
 pub mod state;
+pub mod steam;
 
 use std::{env, net::SocketAddr};
 
@@ -30,10 +34,12 @@ async fn main() {
 
     let state = AppState::new().await;
 
+    jobs::start(state.clone());
+
     let origin = env::var("ORIGIN").unwrap();
 
     let router = Router::new()
-        .nest("/api", api::routes())
+        .nest("/api", api::routes(state.clone()))
         .layer(middleware::map_response(main_response_mapper))
         .layer(CookieManagerLayer::new())
         .layer(
@@ -51,7 +57,7 @@ async fn main() {
     println!("listening on {addr}");
 
     axum::Server::bind(&addr)
-        .serve(router.into_make_service())
+        .serve(router.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .unwrap();
 }
@@ -59,10 +65,11 @@ async fn main() {
 async fn main_response_mapper(uri: Uri, res: Response) -> Response {
     let service_error = res.extensions().get::<Error>();
     let client_status_error = service_error.map(|se| se.client_status_and_error());
+    let client_details = service_error.and_then(|se| se.client_details());
 
-    if service_error.is_some() {
+    if let Some(service_error) = service_error {
         println!("URI - {uri}");
-        println!("ERROR - {:?}", service_error.unwrap());
+        println!("ERROR - {service_error:?}");
         println!();
     }
 
@@ -71,7 +78,9 @@ async fn main_response_mapper(uri: Uri, res: Response) -> Response {
         .map(|(status_code, client_error)| {
             let client_error_body = json!({
                 "error": {
-                    "type": client_error
+                    "type": client_error,
+                    "message": client_error.message(),
+                    "details": client_details,
                 }
             });
 
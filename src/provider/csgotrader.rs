@@ -0,0 +1,39 @@
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::error::{Error, Result};
+
+use super::PriceProvider;
+
+const PRICES_URL: &str = "https://prices.csgotrader.app/latest/prices_v6.json";
+
+pub struct CsgotraderProvider {
+    url: String,
+}
+
+impl CsgotraderProvider {
+    pub fn new(url: Option<String>) -> Self {
+        Self {
+            url: url.unwrap_or_else(|| PRICES_URL.to_string()),
+        }
+    }
+}
+
+#[async_trait]
+impl PriceProvider for CsgotraderProvider {
+    async fn fetch_prices(&self) -> Result<Value> {
+        let client = reqwest::Client::builder()
+            .gzip(true)
+            .build()
+            .map_err(|_| Error::HttpClientCreationFail)?;
+
+        client
+            .get(&self.url)
+            .send()
+            .await
+            .map_err(|_| Error::PricesFetchFail)?
+            .json()
+            .await
+            .map_err(|_| Error::PricesParseFail)
+    }
+}
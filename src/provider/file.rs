@@ -0,0 +1,30 @@
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::error::{Error, Result};
+
+use super::PriceProvider;
+
+/// Reads a local prices_v6 style dump, re-read on every refresh.
+pub struct FileProvider {
+    path: PathBuf,
+}
+
+impl FileProvider {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[async_trait]
+impl PriceProvider for FileProvider {
+    async fn fetch_prices(&self) -> Result<Value> {
+        let data = tokio::fs::read(&self.path)
+            .await
+            .map_err(|_| Error::PricesFetchFail)?;
+
+        serde_json::from_slice(&data).map_err(|_| Error::PricesParseFail)
+    }
+}
@@ -0,0 +1,29 @@
+pub mod csgotrader;
+pub mod file;
+
+use std::{env, sync::Arc};
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::error::Result;
+
+pub use csgotrader::CsgotraderProvider;
+pub use file::FileProvider;
+
+/// Source of the global price snapshot, keyed by market hash name.
+#[async_trait]
+pub trait PriceProvider: Send + Sync {
+    async fn fetch_prices(&self) -> Result<Value>;
+}
+
+/// Picks the provider from `PRICE_PROVIDER` (`csgotrader` or `file`).
+pub fn from_env() -> Arc<dyn PriceProvider> {
+    let provider = env::var("PRICE_PROVIDER").unwrap_or_else(|_| "csgotrader".to_string());
+
+    match provider.as_str() {
+        "file" => Arc::new(FileProvider::new(env::var("PRICES_FILE").unwrap())),
+        "csgotrader" => Arc::new(CsgotraderProvider::new(env::var("PRICES_URL").ok())),
+        other => panic!("unknown PRICE_PROVIDER `{other}`"),
+    }
+}
@@ -1,29 +1,52 @@
-use std::env;
+use std::{env, sync::Arc};
 
 use axum::extract::FromRef;
+use redis::aio::ConnectionManager;
+use tokio::sync::Mutex;
+
+use crate::{
+    db::snapshot::PriceCache,
+    jobs::Jobs,
+    jwt::JwtKeys,
+    provider::{self, PriceProvider},
+};
 
 #[derive(FromRef, Clone)]
 pub struct AppState {
-    pub redis: redis::Client,
+    pub redis: ConnectionManager,
     pub pg: sqlx::postgres::PgPool,
+    pub prices: Arc<dyn PriceProvider>,
+    pub price_refresh: Arc<Mutex<()>>,
+    pub price_cache: Arc<PriceCache>,
+    pub jobs: Arc<Jobs>,
+    pub jwt: Arc<JwtKeys>,
 }
 
 impl AppState {
     pub async fn new() -> Self {
         Self {
-            redis: redis_client(),
+            redis: redis_manager().await,
             pg: pg_pool().await,
+            prices: provider::from_env(),
+            price_refresh: Arc::new(Mutex::new(())),
+            price_cache: Arc::new(PriceCache::default()),
+            jobs: Arc::new(Jobs::default()),
+            jwt: Arc::new(JwtKeys::from_env().await.expect(
+                "TOKEN_PUBLIC_KEY, TOKEN_JWKS_FILE or TOKEN_JWKS_URL is missing or malformed",
+            )),
         }
     }
 }
 
-fn redis_client() -> redis::Client {
+async fn redis_manager() -> ConnectionManager {
     let password = env::var("REDIS_PASSWORD").unwrap();
     let addr = env::var("REDIS_ADDR").unwrap();
 
     let conn_str = format!("redis://default:{password}@{addr}");
 
-    redis::Client::open(conn_str).unwrap()
+    let client = redis::Client::open(conn_str).unwrap();
+
+    ConnectionManager::new(client).await.unwrap()
 }
 
 async fn pg_pool() -> sqlx::postgres::PgPool {
@@ -0,0 +1,314 @@
+use std::{collections::HashMap, env};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+const INVENTORY_URL: &str =
+    "https://steamcommunity.com/inventory/{steam_id}/730/2?l=english&count=1000";
+
+/// Upper bound on pages read for one inventory, in case steam keeps reporting more items.
+const MAX_PAGES: usize = 20;
+
+/// One page of the `/inventory/{steam_id}/730/2` response.
+#[derive(Deserialize, Debug)]
+pub struct InventoryPage {
+    #[serde(default)]
+    pub assets: Vec<Asset>,
+    #[serde(default)]
+    pub descriptions: Vec<Description>,
+    #[serde(default)]
+    pub more_items: Option<i32>,
+    #[serde(default)]
+    pub last_assetid: Option<String>,
+    #[serde(default)]
+    pub total_inventory_count: u32,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Asset {
+    pub assetid: String,
+    pub classid: String,
+    pub instanceid: String,
+    pub amount: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Description {
+    pub classid: String,
+    pub instanceid: String,
+    pub market_hash_name: String,
+    #[serde(default)]
+    pub tradable: i32,
+    #[serde(default)]
+    pub marketable: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct InventoryEntry {
+    pub market_hash_name: String,
+    pub count: u32,
+    pub tradable: u32,
+    pub marketable: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Inventory {
+    pub items: Vec<InventoryEntry>,
+    pub total_inventory_count: u32,
+}
+
+impl Inventory {
+    /// Joins the page's assets to their descriptions by classid/instanceid
+    /// and adds them to the per market hash name counts.
+    pub fn add_page(&mut self, page: &InventoryPage) -> Result<()> {
+        let descriptions: HashMap<(&str, &str), &Description> = page
+            .descriptions
+            .iter()
+            .map(|d| ((d.classid.as_str(), d.instanceid.as_str()), d))
+            .collect();
+
+        self.total_inventory_count = page.total_inventory_count;
+
+        for asset in page.assets.iter() {
+            let desc = descriptions
+                .get(&(asset.classid.as_str(), asset.instanceid.as_str()))
+                .ok_or(Error::SteamMissingDesc)?;
+
+            let amount = asset
+                .amount
+                .parse::<u32>()
+                .map_err(|_| Error::InventoryParseFail)?;
+
+            let tradable = if desc.tradable == 1 { amount } else { 0 };
+
+            match self
+                .items
+                .iter_mut()
+                .find(|i| i.market_hash_name == desc.market_hash_name)
+            {
+                Some(item) => {
+                    item.count += amount;
+                    item.tradable += tradable;
+                }
+                _ => self.items.push(InventoryEntry {
+                    market_hash_name: desc.market_hash_name.clone(),
+                    count: amount,
+                    tradable,
+                    marketable: desc.marketable == 1,
+                }),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Fetches every page of the user's CS inventory.
+///
+/// The endpoint is taken from `STEAM_INVENTORY_URL` with a `{steam_id}` placeholder,
+/// so a local stub can stand in for steamcommunity.com.
+pub async fn fetch_inventory(steam_id: &str) -> Result<Inventory> {
+    let mut client = reqwest::Client::builder();
+
+    if let Ok(proxy_url) = env::var("PROXY_URL") {
+        let proxy = reqwest::Proxy::all(proxy_url).map_err(|_| Error::ProxyCreationFail)?;
+
+        client = client.proxy(proxy);
+    }
+
+    let client = client.build().map_err(|_| Error::HttpClientCreationFail)?;
+
+    let endpoint = env::var("STEAM_INVENTORY_URL")
+        .unwrap_or_else(|_| INVENTORY_URL.to_string())
+        .replace("{steam_id}", steam_id);
+
+    fetch_pages(&client, &endpoint).await
+}
+
+/// Follows `last_assetid` from page to page, reading at most [`MAX_PAGES`].
+///
+/// A page whose `last_assetid` is the one it was requested with is a repeat
+/// of the previous page, so it is dropped and ends the loop.
+async fn fetch_pages(client: &reqwest::Client, endpoint: &str) -> Result<Inventory> {
+    let separator = if endpoint.contains('?') { '&' } else { '?' };
+
+    let mut inventory = Inventory::default();
+    let mut start_assetid: Option<String> = None;
+
+    for _ in 0..MAX_PAGES {
+        let url = match &start_assetid {
+            Some(assetid) => format!("{endpoint}{separator}start_assetid={assetid}"),
+            _ => endpoint.to_string(),
+        };
+
+        let page: InventoryPage = client
+            .get(url)
+            .send()
+            .await
+            .map_err(|_| Error::InventoryFetchFail)?
+            .json()
+            .await
+            .map_err(|_| Error::InventoryParseFail)?;
+
+        if start_assetid.is_some() && page.last_assetid == start_assetid {
+            break;
+        }
+
+        inventory.add_page(&page)?;
+
+        match (page.more_items, page.last_assetid) {
+            (Some(1), Some(last_assetid)) => start_assetid = Some(last_assetid),
+            _ => break,
+        }
+    }
+
+    Ok(inventory)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        net::TcpListener,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use axum::{extract::Query, routing::get, Router};
+
+    use super::*;
+
+    const PAGE_1: &str = include_str!("fixtures/inventory_page_1.json");
+    const PAGE_2: &str = include_str!("fixtures/inventory_page_2.json");
+    const MISSING_DESCRIPTION: &str = include_str!("fixtures/inventory_missing_description.json");
+
+    fn inventory(pages: &[&str]) -> Result<Inventory> {
+        let mut inventory = Inventory::default();
+
+        for page in pages {
+            inventory.add_page(&serde_json::from_str(page).unwrap())?;
+        }
+
+        Ok(inventory)
+    }
+
+    fn entry<'a>(inventory: &'a Inventory, market_hash_name: &str) -> &'a InventoryEntry {
+        inventory
+            .items
+            .iter()
+            .find(|i| i.market_hash_name == market_hash_name)
+            .unwrap()
+    }
+
+    /// Serves `app` on a local port, returning the inventory endpoint on it.
+    fn serve(app: Router) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(app.into_make_service());
+
+        tokio::spawn(server);
+
+        format!("http://{addr}/inventory?l=english&count=5")
+    }
+
+    #[test]
+    fn duplicate_assets_collapse_into_one_count() {
+        let inventory = inventory(&[PAGE_1]).unwrap();
+
+        let redline = entry(&inventory, "AK-47 | Redline (Field-Tested)");
+
+        assert_eq!(redline.count, 3);
+        assert_eq!(redline.tradable, 3);
+        assert_eq!(
+            inventory
+                .items
+                .iter()
+                .filter(|i| i.market_hash_name == redline.market_hash_name)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn stackable_amount_is_counted() {
+        let inventory = inventory(&[PAGE_1]).unwrap();
+
+        let graffiti = entry(&inventory, "Sealed Graffiti | GGWP (Tiger Orange)");
+
+        assert_eq!(graffiti.count, 5);
+        assert_eq!(graffiti.tradable, 5);
+    }
+
+    #[test]
+    fn untradable_and_unmarketable_items() {
+        let inventory = inventory(&[PAGE_1, PAGE_2]).unwrap();
+
+        let case = entry(&inventory, "Prisma 2 Case");
+        let medal = entry(&inventory, "2023 Service Medal");
+
+        assert_eq!((case.count, case.tradable, case.marketable), (1, 0, true));
+        assert_eq!(
+            (medal.count, medal.tradable, medal.marketable),
+            (1, 0, false)
+        );
+    }
+
+    #[test]
+    fn asset_without_description_fails() {
+        assert!(matches!(
+            inventory(&[MISSING_DESCRIPTION]),
+            Err(Error::SteamMissingDesc)
+        ));
+    }
+
+    #[tokio::test]
+    async fn follows_last_assetid_to_the_next_page() {
+        let app = Router::new().route(
+            "/inventory",
+            get(|Query(query): Query<HashMap<String, String>>| async move {
+                match query.get("start_assetid").map(String::as_str) {
+                    None => PAGE_1,
+                    Some("30005") => PAGE_2,
+                    Some(_) => "{}",
+                }
+            }),
+        );
+
+        let inventory = fetch_pages(&reqwest::Client::new(), &serve(app))
+            .await
+            .unwrap();
+
+        assert_eq!(inventory.total_inventory_count, 7);
+        assert_eq!(inventory.items.len(), 4);
+        assert_eq!(entry(&inventory, "AK-47 | Redline (Field-Tested)").count, 4);
+        assert_eq!(entry(&inventory, "2023 Service Medal").count, 1);
+    }
+
+    #[tokio::test]
+    async fn stops_when_last_assetid_repeats() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+
+        let app = Router::new().route(
+            "/inventory",
+            get(move || async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+
+                PAGE_1
+            }),
+        );
+
+        let inventory = fetch_pages(&reqwest::Client::new(), &serve(app))
+            .await
+            .unwrap();
+
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+        assert_eq!(entry(&inventory, "AK-47 | Redline (Field-Tested)").count, 3);
+    }
+}
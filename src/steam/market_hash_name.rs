@@ -0,0 +1,311 @@
+use serde::{Deserialize, Serialize};
+
+const STAR: &str = "★";
+const STATTRAK: &str = "StatTrak™";
+const SOUVENIR: &str = "Souvenir";
+
+/// Finishes whose variants share one market hash name and differ by phase.
+const PHASED_FINISHES: [&str; 2] = ["Gamma Doppler", "Doppler"];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exterior {
+    #[serde(rename = "Factory New")]
+    FactoryNew,
+    #[serde(rename = "Minimal Wear")]
+    MinimalWear,
+    #[serde(rename = "Field-Tested")]
+    FieldTested,
+    #[serde(rename = "Well-Worn")]
+    WellWorn,
+    #[serde(rename = "Battle-Scarred")]
+    BattleScarred,
+}
+
+impl Exterior {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::FactoryNew => "Factory New",
+            Self::MinimalWear => "Minimal Wear",
+            Self::FieldTested => "Field-Tested",
+            Self::WellWorn => "Well-Worn",
+            Self::BattleScarred => "Battle-Scarred",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Factory New" => Some(Self::FactoryNew),
+            "Minimal Wear" => Some(Self::MinimalWear),
+            "Field-Tested" => Some(Self::FieldTested),
+            "Well-Worn" => Some(Self::WellWorn),
+            "Battle-Scarred" => Some(Self::BattleScarred),
+            _ => None,
+        }
+    }
+}
+
+/// A market hash name split into its parts, e.g.
+/// `★ StatTrak™ Karambit | Doppler (Factory New)`.
+///
+/// Parsing never fails, names that do not follow the pattern end up whole in `base`.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct MarketHashName {
+    pub star: bool,
+    pub stattrak: bool,
+    pub souvenir: bool,
+    /// Weapon or item kind, `AK-47`, `Karambit`, `Sticker`, `Music Kit`.
+    pub base: String,
+    /// Everything after the first ` | `, `Redline` or `Crown (Foil)`.
+    pub finish: Option<String>,
+    pub exterior: Option<Exterior>,
+    /// Doppler phase or gem, only present when the name spells it out.
+    pub phase: Option<String>,
+}
+
+impl MarketHashName {
+    pub fn parse(name: &str) -> Self {
+        let mut rest = name.trim();
+
+        let star = strip_word(&mut rest, STAR);
+        let stattrak = strip_word(&mut rest, STATTRAK);
+        let souvenir = strip_word(&mut rest, SOUVENIR);
+
+        let mut phase = None;
+
+        if let Some((head, tail)) = rest.rsplit_once(" - ") {
+            if is_phase(tail) {
+                phase = Some(tail.to_string());
+                rest = head;
+            }
+        }
+
+        let mut exterior = None;
+
+        if let Some(head) = rest.strip_suffix(')') {
+            if let Some((head, wear)) = head.rsplit_once(" (") {
+                if let Some(wear) = Exterior::from_name(wear) {
+                    exterior = Some(wear);
+                    rest = head;
+                }
+            }
+        }
+
+        let (base, mut finish) = match rest.split_once(" | ") {
+            Some((base, finish)) => (base.to_string(), Some(finish.to_string())),
+            _ => (rest.to_string(), None),
+        };
+
+        if let Some(current) = finish.as_deref() {
+            for phased in PHASED_FINISHES {
+                let Some(tail) = current.strip_prefix(phased) else {
+                    continue;
+                };
+
+                if let Some(tail) = tail.strip_prefix(' ').filter(|t| is_phase(t)) {
+                    phase = Some(tail.to_string());
+                    finish = Some(phased.to_string());
+                }
+
+                break;
+            }
+        }
+
+        Self {
+            star,
+            stattrak,
+            souvenir,
+            base,
+            finish,
+            exterior,
+            phase,
+        }
+    }
+}
+
+/// Removes `word` and the space after it from the start of `rest`.
+fn strip_word(rest: &mut &str, word: &str) -> bool {
+    match rest.strip_prefix(word) {
+        Some(tail) if tail.starts_with(' ') => {
+            *rest = tail.trim_start();
+            true
+        }
+        _ => false,
+    }
+}
+
+fn is_phase(name: &str) -> bool {
+    matches!(
+        name,
+        "Phase 1"
+            | "Phase 2"
+            | "Phase 3"
+            | "Phase 4"
+            | "Ruby"
+            | "Sapphire"
+            | "Black Pearl"
+            | "Emerald"
+    )
+}
+
+/// Names with ™, ★, quotes and extra pipes, for tests of every place that looks items up by name.
+#[cfg(test)]
+pub const NAME_CORPUS: [&str; 8] = [
+    "★ StatTrak™ Karambit | Doppler (Factory New)",
+    "★ Butterfly Knife",
+    "StatTrak™ M4A4 | Howl (Minimal Wear)",
+    "Souvenir AWP | Dragon Lore (Field-Tested)",
+    "Sticker | Ninjas in Pyjamas (Holo) | Katowice 2014",
+    "'Blueberries' Buckshot | NSWC SEAL",
+    "Sealed Graffiti | \"GG\" (Tiger Orange)",
+    "StatTrak™ Music Kit | Daniel Sadowski, Crimson Assault",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use Exterior::*;
+
+    /// `(name, star, stattrak, souvenir, base, finish, exterior, phase)`
+    type Case = (
+        &'static str,
+        bool,
+        bool,
+        bool,
+        &'static str,
+        Option<&'static str>,
+        Option<Exterior>,
+        Option<&'static str>,
+    );
+
+    const CASES: [Case; 12] = [
+        (
+            "★ StatTrak™ Karambit | Doppler (Factory New)",
+            true,
+            true,
+            false,
+            "Karambit",
+            Some("Doppler"),
+            Some(FactoryNew),
+            None,
+        ),
+        (
+            "★ Karambit | Gamma Doppler Emerald",
+            true,
+            false,
+            false,
+            "Karambit",
+            Some("Gamma Doppler"),
+            None,
+            Some("Emerald"),
+        ),
+        (
+            "★ Karambit | Doppler (Factory New) - Phase 2",
+            true,
+            false,
+            false,
+            "Karambit",
+            Some("Doppler"),
+            Some(FactoryNew),
+            Some("Phase 2"),
+        ),
+        (
+            "Sticker | Crown (Foil)",
+            false,
+            false,
+            false,
+            "Sticker",
+            Some("Crown (Foil)"),
+            None,
+            None,
+        ),
+        (
+            "Souvenir AWP | Dragon Lore (Field-Tested)",
+            false,
+            false,
+            true,
+            "AWP",
+            Some("Dragon Lore"),
+            Some(FieldTested),
+            None,
+        ),
+        (
+            "StatTrak™ Music Kit | Noisia, Sharpened",
+            false,
+            true,
+            false,
+            "Music Kit",
+            Some("Noisia, Sharpened"),
+            None,
+            None,
+        ),
+        (
+            "(Factory New)",
+            false,
+            false,
+            false,
+            "(Factory New)",
+            None,
+            None,
+            None,
+        ),
+        (
+            "Prisma 2 Case",
+            false,
+            false,
+            false,
+            "Prisma 2 Case",
+            None,
+            None,
+            None,
+        ),
+        (
+            "★ Butterfly Knife",
+            true,
+            false,
+            false,
+            "Butterfly Knife",
+            None,
+            None,
+            None,
+        ),
+        (
+            "'Blueberries' Buckshot | NSWC SEAL",
+            false,
+            false,
+            false,
+            "'Blueberries' Buckshot",
+            Some("NSWC SEAL"),
+            None,
+            None,
+        ),
+        (
+            "Sticker | Ninjas in Pyjamas (Holo) | Katowice 2014",
+            false,
+            false,
+            false,
+            "Sticker",
+            Some("Ninjas in Pyjamas (Holo) | Katowice 2014"),
+            None,
+            None,
+        ),
+        ("", false, false, false, "", None, None, None),
+    ];
+
+    #[test]
+    fn parses_names() {
+        for (name, star, stattrak, souvenir, base, finish, exterior, phase) in CASES {
+            let expected = MarketHashName {
+                star,
+                stattrak,
+                souvenir,
+                base: base.to_string(),
+                finish: finish.map(str::to_string),
+                exterior,
+                phase: phase.map(str::to_string),
+            };
+
+            assert_eq!(MarketHashName::parse(name), expected, "{name:?}");
+        }
+    }
+}